@@ -0,0 +1,363 @@
+use std::collections::BTreeMap;
+
+use anyhow::anyhow;
+use datafusion::arrow::array::AsArray;
+use datafusion::arrow::compute::cast;
+use datafusion::arrow::datatypes::{DataType, Float64Type};
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::functions::expr_fn::floor;
+use datafusion::functions_aggregate::expr_fn::sum;
+use datafusion::logical_expr::{avg, cast as cast_expr, count, max, min, when, Expr};
+use datafusion::prelude::{ident, lit, DataFrame};
+
+use crate::profile::{as_float, has_histogram};
+
+/// Points plotted as they are, larger results are downsampled.
+const MAX_POINTS: usize = 10_000;
+/// Buckets along the x axis that each series of a downsampled result is averaged into.
+const DOWNSAMPLE_BUCKETS: usize = 1_000;
+/// Bins of a histogram.
+const HISTOGRAM_BINS: usize = 50;
+/// Categories of a bar chart, those with the largest values are kept.
+const MAX_BARS: usize = 50;
+/// Series of a grouped chart, the most frequent groups are kept.
+const MAX_GROUPS: usize = 10;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ChartKind {
+    Line,
+    Scatter,
+    Bar,
+    Histogram,
+}
+
+impl ChartKind {
+    pub const ALL: [ChartKind; 4] = [
+        ChartKind::Line,
+        ChartKind::Scatter,
+        ChartKind::Bar,
+        ChartKind::Histogram,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChartKind::Line => "Line",
+            ChartKind::Scatter => "Scatter",
+            ChartKind::Bar => "Bar",
+            ChartKind::Histogram => "Histogram",
+        }
+    }
+
+    /// Whether the chart plots a y column. Bar charts count rows without one.
+    pub fn has_y(&self) -> bool {
+        !matches!(self, ChartKind::Histogram)
+    }
+}
+
+/// Columns of a result to chart, and how.
+#[derive(PartialEq, Clone, Debug)]
+pub struct ChartSpec {
+    pub kind: ChartKind,
+    pub x: String,
+    pub y: Option<String>,
+    /// Column whose values split the rows into a series each.
+    pub group: Option<String>,
+}
+
+pub struct Series {
+    pub name: String,
+    pub points: Vec<[f64; 2]>,
+}
+
+pub struct ChartData {
+    pub spec: ChartSpec,
+    pub series: Vec<Series>,
+    /// Type of the x column, to label the axis with its values.
+    pub x_type: DataType,
+    /// Names of the bars of a bar chart, by position along the x axis.
+    pub categories: Vec<String>,
+    pub bin_width: f64,
+    /// Whether each series was averaged into buckets, as it had too many points to plot.
+    pub downsampled: bool,
+}
+
+fn float_column(batch: &RecordBatch, index: usize) -> anyhow::Result<Vec<Option<f64>>> {
+    let array = cast(batch.column(index), &DataType::Float64)?;
+    Ok(array.as_primitive::<Float64Type>().iter().collect())
+}
+
+fn string_column(batch: &RecordBatch, index: usize) -> anyhow::Result<Vec<Option<String>>> {
+    let array = cast(batch.column(index), &DataType::Utf8)?;
+    Ok(array
+        .as_string::<i32>()
+        .iter()
+        .map(|value| value.map(str::to_owned))
+        .collect())
+}
+
+/// Lowest and highest value of `x`, if it has any values.
+async fn bounds(df: &DataFrame, x: &Expr) -> anyhow::Result<Option<(f64, f64)>> {
+    let batches = df
+        .clone()
+        .aggregate(vec![], vec![min(x.clone()), max(x.clone())])?
+        .collect()
+        .await?;
+    let batch = batches
+        .iter()
+        .find(|batch| batch.num_rows() > 0)
+        .ok_or(anyhow!("Chart query returned no rows"))?;
+    Ok(float_column(batch, 0)?[0].zip(float_column(batch, 1)?[0]))
+}
+
+/// Index of the bucket of width `width` starting from `low` that `x` falls in. The highest value
+/// falls on the end of the last bucket, rather than the start of a new one.
+fn bucket(x: Expr, low: f64, width: f64, buckets: usize) -> anyhow::Result<Expr> {
+    if width <= 0.0 {
+        return Ok(lit(0.0));
+    }
+    let bucket = floor((x - lit(low)) / lit(width));
+    Ok(when(
+        bucket.clone().gt_eq(lit((buckets - 1) as f64)),
+        lit((buckets - 1) as f64),
+    )
+    .otherwise(bucket)?)
+}
+
+type Row = (Option<String>, Option<f64>, Option<f64>);
+
+/// Rows of batches of group, x and y columns.
+fn rows(batches: &[RecordBatch]) -> anyhow::Result<Vec<Row>> {
+    let mut rows = vec![];
+    for batch in batches {
+        let group = string_column(batch, 0)?;
+        let x = float_column(batch, 1)?;
+        let y = float_column(batch, 2)?;
+        rows.extend(
+            group
+                .into_iter()
+                .zip(x)
+                .zip(y)
+                .map(|((group, x), y)| (group, x, y)),
+        );
+    }
+    Ok(rows)
+}
+
+/// Split rows into a series per group, in the order the groups were listed.
+fn collect_series(rows: Vec<Row>, groups: &[String], default_name: &str) -> Vec<Series> {
+    let mut points: BTreeMap<String, Vec<[f64; 2]>> = BTreeMap::new();
+    for (group, x, y) in rows {
+        if let (Some(x), Some(y)) = (x, y) {
+            points
+                .entry(group.unwrap_or_default())
+                .or_default()
+                .push([x, y]);
+        }
+    }
+    if groups.is_empty() {
+        vec![Series {
+            name: default_name.to_owned(),
+            points: points.into_values().flatten().collect(),
+        }]
+    } else {
+        groups
+            .iter()
+            .map(|group| Series {
+                name: group.clone(),
+                points: points.remove(group).unwrap_or_default(),
+            })
+            .collect()
+    }
+}
+
+/// Fetch the points of a chart of `df`. Results with more points than can usefully be drawn are
+/// aggregated by DataFusion, rather than fetched and drawn row by row.
+pub async fn chart_data(df: DataFrame, spec: ChartSpec) -> anyhow::Result<ChartData> {
+    let x_type = df
+        .schema()
+        .field_with_unqualified_name(&spec.x)?
+        .data_type()
+        .clone();
+    let y = match (&spec.y, spec.kind.has_y()) {
+        (Some(y), true) => {
+            let data_type = df.schema().field_with_unqualified_name(y)?.data_type();
+            if !data_type.is_numeric() {
+                return Err(anyhow!("Can't plot {}, which is {}", y, data_type));
+            }
+            Some(cast_expr(ident(y), DataType::Float64))
+        }
+        (None, true) if spec.kind != ChartKind::Bar => {
+            return Err(anyhow!("Pick a column to plot against {}", spec.x));
+        }
+        _ => None,
+    };
+    if spec.kind != ChartKind::Bar && !has_histogram(&x_type) {
+        return Err(anyhow!(
+            "{} is {}, a {} chart needs numbers, dates or times along its x axis",
+            spec.x,
+            x_type,
+            spec.kind.name().to_lowercase()
+        ));
+    }
+
+    let mut df = df.filter(ident(&spec.x).is_not_null())?;
+    if let Some(y) = &y {
+        df = df.filter(y.clone().is_not_null())?;
+    }
+
+    // limit the groups to the most frequent, so that the legend stays readable
+    let mut groups = vec![];
+    let group = match &spec.group {
+        Some(group) => {
+            let group = cast_expr(ident(group), DataType::Utf8);
+            let batches = df
+                .clone()
+                .aggregate(
+                    vec![group.clone().alias("group")],
+                    vec![count(lit(1)).alias("rows")],
+                )?
+                .sort(vec![ident("rows").sort(false, false)])?
+                .limit(0, Some(MAX_GROUPS))?
+                .collect()
+                .await?;
+            for batch in &batches {
+                groups.extend(string_column(batch, 0)?.into_iter().flatten());
+            }
+            df = df.filter(
+                group
+                    .clone()
+                    .in_list(groups.iter().map(lit).collect(), false),
+            )?;
+            group
+        }
+        None => lit(""),
+    };
+    let default_name = spec.y.as_deref().unwrap_or("rows").to_owned();
+
+    let mut categories = vec![];
+    let mut bin_width = 0.0;
+    let mut downsampled = false;
+    let series = match spec.kind {
+        ChartKind::Line | ChartKind::Scatter => {
+            let x = as_float(ident(&spec.x), &x_type);
+            let y = y.expect("Checked above");
+            let points = if df.clone().count().await? <= MAX_POINTS {
+                df.select(vec![group.alias("group"), x.alias("x"), y.alias("y")])?
+            } else {
+                downsampled = true;
+                match bounds(&df, &x).await? {
+                    Some((low, high)) => {
+                        let width = (high - low) / DOWNSAMPLE_BUCKETS as f64;
+                        let bucket = bucket(x.clone(), low, width, DOWNSAMPLE_BUCKETS)?;
+                        df.aggregate(
+                            vec![group.alias("group"), bucket.alias("bucket")],
+                            vec![avg(x).alias("x"), avg(y).alias("y")],
+                        )?
+                        .select(vec![
+                            ident("group"),
+                            ident("x"),
+                            ident("y"),
+                        ])?
+                    }
+                    None => df.select(vec![group.alias("group"), x.alias("x"), y.alias("y")])?,
+                }
+            };
+            let batches = points
+                .sort(vec![ident("x").sort(true, false)])?
+                .collect()
+                .await?;
+            collect_series(rows(&batches)?, &groups, &default_name)
+        }
+        ChartKind::Histogram => {
+            let x = as_float(ident(&spec.x), &x_type);
+            match bounds(&df, &x).await? {
+                Some((low, high)) => {
+                    bin_width = (high - low) / HISTOGRAM_BINS as f64;
+                    let bin = bucket(x, low, bin_width, HISTOGRAM_BINS)?;
+                    if bin_width <= 0.0 {
+                        bin_width = 1.0;
+                    }
+                    let batches = df
+                        .aggregate(
+                            vec![group.alias("group"), bin.alias("bin")],
+                            vec![count(lit(1)).alias("rows")],
+                        )?
+                        .select(vec![
+                            ident("group"),
+                            (lit(low) + ident("bin") * lit(bin_width)).alias("x"),
+                            ident("rows"),
+                        ])?
+                        .collect()
+                        .await?;
+                    collect_series(rows(&batches)?, &groups, &default_name)
+                }
+                None => vec![],
+            }
+        }
+        ChartKind::Bar => {
+            let value = match y {
+                Some(y) => sum(y),
+                None => count(lit(1)),
+            };
+            let label = cast_expr(ident(&spec.x), DataType::Utf8);
+            // the largest bars, ordered by their value of x
+            let batches = df
+                .clone()
+                .aggregate(vec![ident(&spec.x)], vec![value.clone().alias("value")])?
+                .sort(vec![ident("value").sort(false, false)])?
+                .limit(0, Some(MAX_BARS))?
+                .sort(vec![ident(&spec.x).sort(true, false)])?
+                .select(vec![label.clone().alias("label")])?
+                .collect()
+                .await?;
+            for batch in &batches {
+                categories.extend(string_column(batch, 0)?.into_iter().flatten());
+            }
+            let batches = df
+                .filter(
+                    label
+                        .clone()
+                        .in_list(categories.iter().map(lit).collect(), false),
+                )?
+                .aggregate(
+                    vec![group.alias("group"), label.alias("label")],
+                    vec![value.alias("value")],
+                )?
+                .collect()
+                .await?;
+            // bars are placed by the position of their category
+            let positions: BTreeMap<&str, usize> = categories
+                .iter()
+                .enumerate()
+                .map(|(i, category)| (category.as_str(), i))
+                .collect();
+            let mut placed = vec![];
+            for batch in &batches {
+                let group = string_column(batch, 0)?;
+                let label = string_column(batch, 1)?;
+                let value = float_column(batch, 2)?;
+                for ((group, label), value) in group.into_iter().zip(label).zip(value) {
+                    let position = label
+                        .as_deref()
+                        .and_then(|label| positions.get(label))
+                        .map(|&i| i as f64);
+                    placed.push((group, position, value));
+                }
+            }
+            let mut series = collect_series(placed, &groups, &default_name);
+            for series in &mut series {
+                series.points.sort_by(|a, b| a[0].total_cmp(&b[0]));
+            }
+            series
+        }
+    };
+
+    Ok(ChartData {
+        spec,
+        series,
+        x_type,
+        categories,
+        bin_width,
+        downsampled,
+    })
+}
@@ -0,0 +1,205 @@
+use std::io::Write;
+use std::str::FromStr;
+
+use crate::components::{format_bytes, format_statistics};
+use crate::data::{is_parquet, parquet_metadata, DataSource, Query, TableDescriptor};
+use anyhow::anyhow;
+use async_compat::Compat;
+use datafusion::arrow::{csv, json, util::pretty::pretty_format_batches};
+use datafusion::prelude::DataFrame;
+use itertools::Itertools;
+use smol::stream::StreamExt;
+use structopt::StructOpt;
+
+#[derive(Debug)]
+pub enum OutputFormat {
+    Csv,
+    Json,
+    NdJson,
+    Table,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(OutputFormat::Csv),
+            "json" => Ok(OutputFormat::Json),
+            "ndjson" => Ok(OutputFormat::NdJson),
+            "table" => Ok(OutputFormat::Table),
+            _ => Err(anyhow!("Unknown output format {}", s)),
+        }
+    }
+}
+
+// headless commands, which print to stdout rather than opening a window
+#[derive(StructOpt, Debug)]
+pub enum Command {
+    /// Run a query against a file and print the result.
+    Query {
+        filename: String,
+        /// SQL to run, the file is available under its table name. Defaults to the whole table.
+        #[structopt(short, long)]
+        sql: Option<String>,
+        #[structopt(short, long, default_value = "table", possible_values = &["csv", "json", "ndjson", "table"])]
+        format: OutputFormat,
+        /// Table name to register the file as, defaults to the file name.
+        #[structopt(short, long)]
+        table_name: Option<String>,
+    },
+    /// Print the schema of a file.
+    Schema { filename: String },
+    /// Print the key/value metadata of a file, and the footer of parquet files.
+    Meta { filename: String },
+}
+
+pub fn run(command: Command) -> anyhow::Result<()> {
+    smol::block_on(Compat::new(async {
+        let mut data_source = DataSource::default();
+        match command {
+            Command::Query {
+                filename,
+                sql,
+                format,
+                table_name,
+            } => {
+                let mut table = TableDescriptor::new(&filename)?;
+                if let Some(table_name) = table_name.as_ref() {
+                    table = table.with_table_name(table_name);
+                }
+                let table_name = data_source.add_data_source(table).await?;
+                let query = match sql {
+                    Some(sql) => Query::Sql(sql),
+                    None => Query::TableName(table_name),
+                };
+                let df = data_source.dataframe(query).await?;
+                print_query(df, format).await
+            }
+            Command::Schema { filename } => {
+                let table_name = data_source
+                    .add_data_source(TableDescriptor::new(&filename)?)
+                    .await?;
+                let df = data_source.dataframe(Query::TableName(table_name)).await?;
+                let mut out = std::io::stdout().lock();
+                for field in df.schema().fields() {
+                    writeln!(
+                        out,
+                        "{}: {}{}",
+                        field.name(),
+                        field.data_type(),
+                        if field.is_nullable() { "" } else { " not null" }
+                    )?;
+                }
+                Ok(())
+            }
+            Command::Meta { filename } => {
+                let table_name = data_source
+                    .add_data_source(TableDescriptor::new(&filename)?)
+                    .await?;
+                print_meta(&mut data_source, &table_name).await
+            }
+        }
+    }))
+}
+
+async fn print_query(df: DataFrame, format: OutputFormat) -> anyhow::Result<()> {
+    let mut out = std::io::stdout().lock();
+
+    // the table layout depends on every value, everything else is written as it arrives
+    if let OutputFormat::Table = format {
+        writeln!(out, "{}", pretty_format_batches(&df.collect().await?)?)?;
+        return Ok(());
+    }
+
+    let mut stream = df.execute_stream().await?;
+    match format {
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::new(out);
+            while let Some(batch) = stream.next().await {
+                writer.write(&batch?)?;
+            }
+        }
+        OutputFormat::Json => {
+            let mut writer = json::ArrayWriter::new(out);
+            while let Some(batch) = stream.next().await {
+                writer.write(&batch?)?;
+            }
+            writer.finish()?;
+            // the array ends without a newline, unlike the other formats
+            writeln!(writer.into_inner())?;
+        }
+        OutputFormat::NdJson => {
+            let mut writer = json::LineDelimitedWriter::new(out);
+            while let Some(batch) = stream.next().await {
+                writer.write(&batch?)?;
+            }
+            writer.finish()?;
+        }
+        OutputFormat::Table => unreachable!("Handled above"),
+    }
+    Ok(())
+}
+
+async fn print_meta(data_source: &mut DataSource, table_name: &str) -> anyhow::Result<()> {
+    let table = data_source
+        .list_tables()
+        .await
+        .get(table_name)
+        .cloned()
+        .ok_or(anyhow!("Could not load table {}", table_name))?;
+
+    let mut out = std::io::stdout().lock();
+    for (key, value) in table.schema().metadata().iter().sorted() {
+        writeln!(out, "{}: {}", key, value)?;
+    }
+
+    if !is_parquet(table.as_ref()) {
+        return Ok(());
+    }
+
+    for (path, metadata) in parquet_metadata(table.as_ref(), &data_source.state()).await? {
+        let file = metadata.file_metadata();
+        writeln!(out, "{}", path)?;
+        writeln!(out, "  rows: {}", file.num_rows())?;
+        writeln!(out, "  row groups: {}", metadata.num_row_groups())?;
+        writeln!(out, "  format version: {}", file.version())?;
+        writeln!(
+            out,
+            "  created by: {}",
+            file.created_by().unwrap_or_default()
+        )?;
+        for (i, row_group) in metadata.row_groups().iter().enumerate() {
+            writeln!(
+                out,
+                "  row group {}: {} rows, {} ({} uncompressed)",
+                i,
+                row_group.num_rows(),
+                format_bytes(row_group.compressed_size()),
+                format_bytes(row_group.total_byte_size()),
+            )?;
+            for chunk in row_group.columns() {
+                let (min, max) = chunk
+                    .statistics()
+                    .map(format_statistics)
+                    .unwrap_or_default();
+                writeln!(
+                    out,
+                    "    {}: {} {} [{}] {} ({} uncompressed) min: {} max: {} nulls: {}",
+                    chunk.column_path().string(),
+                    chunk.column_type(),
+                    chunk.compression(),
+                    chunk.encodings().iter().join(", "),
+                    format_bytes(chunk.compressed_size()),
+                    format_bytes(chunk.uncompressed_size()),
+                    min,
+                    max,
+                    chunk
+                        .statistics()
+                        .map_or("".to_owned(), |stats| stats.null_count().to_string()),
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,374 @@
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use datafusion::arrow::array::{Array, ArrayRef, StringArray};
+use datafusion::arrow::compute::concat_batches;
+use datafusion::arrow::csv::WriterBuilder;
+use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use datafusion::arrow::json;
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::arrow::util::display::array_value_to_string;
+use itertools::Itertools;
+
+/// Rows copied at most, larger selections should be exported instead.
+pub const MAX_COPY_ROWS: usize = 100_000;
+
+/// Cells of a result picked by clicking, shift-clicking or dragging, as a rectangle from the cell
+/// the selection started at to the one it was extended to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Selection {
+    /// Row and column the selection started at.
+    pub anchor: (usize, usize),
+    /// Row and column the selection extends to.
+    pub cursor: (usize, usize),
+    /// Every column of the selected rows, picked from the row numbers.
+    pub whole_rows: bool,
+    /// Every row of the selected columns, picked from the column headers.
+    pub whole_columns: bool,
+}
+
+impl Selection {
+    pub fn cell(row: usize, column: usize) -> Self {
+        Self {
+            anchor: (row, column),
+            cursor: (row, column),
+            whole_rows: false,
+            whole_columns: false,
+        }
+    }
+
+    pub fn row(row: usize) -> Self {
+        Self {
+            whole_rows: true,
+            ..Self::cell(row, 0)
+        }
+    }
+
+    pub fn column(column: usize) -> Self {
+        Self {
+            whole_columns: true,
+            ..Self::cell(0, column)
+        }
+    }
+
+    /// The selection extended to the cell at `row` and `column`, keeping where it started.
+    pub fn extend_to(self, row: usize, column: usize) -> Self {
+        Self {
+            cursor: (row, column),
+            ..self
+        }
+    }
+
+    /// First selected row, and the number of rows, or None if every row is selected.
+    pub fn rows(&self) -> (usize, Option<usize>) {
+        if self.whole_columns {
+            return (0, None);
+        }
+        let (first, last) = ordered(self.anchor.0, self.cursor.0);
+        (first, Some(last - first + 1))
+    }
+
+    /// Selected columns out of `columns`.
+    pub fn columns(&self, columns: usize) -> RangeInclusive<usize> {
+        if self.whole_rows {
+            return 0..=columns.saturating_sub(1);
+        }
+        let (first, last) = ordered(self.anchor.1, self.cursor.1);
+        first..=last
+    }
+
+    pub fn contains(&self, row: usize, column: usize) -> bool {
+        let in_rows = self.whole_columns || {
+            let (first, last) = ordered(self.anchor.0, self.cursor.0);
+            (first..=last).contains(&row)
+        };
+        let in_columns = self.whole_rows || {
+            let (first, last) = ordered(self.anchor.1, self.cursor.1);
+            (first..=last).contains(&column)
+        };
+        in_rows && in_columns
+    }
+
+    /// Whether a single cell is selected.
+    pub fn is_cell(&self) -> bool {
+        !self.whole_rows && !self.whole_columns && self.anchor == self.cursor
+    }
+
+    /// Whether the selection is within a single column, so can be copied as a list of values.
+    pub fn is_column(&self) -> bool {
+        !self.whole_rows && self.anchor.1 == self.cursor.1
+    }
+}
+
+fn ordered(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum CopyFormat {
+    /// Tab separated, which pastes into spreadsheets as cells.
+    Tsv,
+    Csv,
+    Json,
+    Markdown,
+    /// A `VALUES` list with a row for each row.
+    SqlValues,
+    /// An `IN (...)` list of the values of a single column.
+    InList,
+}
+
+impl CopyFormat {
+    /// Formats any selection can be copied as, [`CopyFormat::InList`] also needs a single column.
+    pub const ALL: [CopyFormat; 5] = [
+        CopyFormat::Tsv,
+        CopyFormat::Csv,
+        CopyFormat::Json,
+        CopyFormat::Markdown,
+        CopyFormat::SqlValues,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CopyFormat::Tsv => "TSV",
+            CopyFormat::Csv => "CSV",
+            CopyFormat::Json => "JSON",
+            CopyFormat::Markdown => "Markdown table",
+            CopyFormat::SqlValues => "SQL VALUES list",
+            CopyFormat::InList => "IN (...) list",
+        }
+    }
+}
+
+/// The value as a SQL literal, quoting anything that isn't a number or boolean.
+fn sql_literal(array: &ArrayRef, row: usize) -> anyhow::Result<String> {
+    if array.is_null(row) {
+        return Ok("NULL".to_owned());
+    }
+    let value = array_value_to_string(array, row)?;
+    Ok(match array.data_type() {
+        data_type if data_type.is_numeric() => value,
+        DataType::Boolean => value.to_uppercase(),
+        _ => format!("'{}'", value.replace('\'', "''")),
+    })
+}
+
+/// The batch with its nested columns, which the CSV writer rejects, replaced by their values as
+/// they're displayed.
+fn nested_as_text(batch: &RecordBatch) -> anyhow::Result<RecordBatch> {
+    let schema = batch.schema();
+    let mut fields = vec![];
+    let mut columns = vec![];
+    for (field, column) in schema.fields().iter().zip(batch.columns()) {
+        if field.data_type().is_nested() {
+            let values = (0..column.len())
+                .map(|row| {
+                    (!column.is_null(row))
+                        .then(|| array_value_to_string(column, row))
+                        .transpose()
+                })
+                .collect::<Result<StringArray, _>>()?;
+            fields.push(Field::new(field.name(), DataType::Utf8, true));
+            columns.push(Arc::new(values) as ArrayRef);
+        } else {
+            fields.push(field.as_ref().clone());
+            columns.push(column.clone());
+        }
+    }
+    Ok(RecordBatch::try_new(
+        Arc::new(Schema::new(fields)),
+        columns,
+    )?)
+}
+
+fn delimited(batch: &RecordBatch, delimiter: u8, header: bool) -> anyhow::Result<String> {
+    let mut writer = WriterBuilder::new()
+        .with_delimiter(delimiter)
+        .with_header(header)
+        .build(vec![]);
+    writer.write(&nested_as_text(batch)?)?;
+    let text = String::from_utf8(writer.into_inner())?;
+    Ok(text.trim_end_matches('\n').to_owned())
+}
+
+/// Format copied rows as text. A single cell is copied as its bare value, unless the format needs
+/// column names.
+pub fn format_selection(
+    schema: SchemaRef,
+    batches: &[RecordBatch],
+    format: CopyFormat,
+    single_cell: bool,
+) -> anyhow::Result<String> {
+    let batch = concat_batches(
+        &batches
+            .first()
+            .map(|batch| batch.schema())
+            .unwrap_or(schema),
+        batches,
+    )?;
+    let rows = 0..batch.num_rows();
+    let text = match format {
+        CopyFormat::Tsv | CopyFormat::Csv if single_cell => {
+            if batch.num_rows() == 0 || batch.column(0).is_null(0) {
+                "".to_owned()
+            } else {
+                array_value_to_string(batch.column(0), 0)?
+            }
+        }
+        CopyFormat::Tsv => delimited(&batch, b'\t', true)?,
+        CopyFormat::Csv => delimited(&batch, b',', true)?,
+        CopyFormat::Json => {
+            let mut writer = json::ArrayWriter::new(vec![]);
+            writer.write(&batch)?;
+            writer.finish()?;
+            let value: serde_json::Value = serde_json::from_slice(&writer.into_inner())?;
+            serde_json::to_string_pretty(&value)?
+        }
+        CopyFormat::Markdown => {
+            let cell = |text: String| text.replace('|', "\\|").replace('\n', " ");
+            let schema = batch.schema();
+            let mut lines = vec![
+                format!(
+                    "| {} |",
+                    schema
+                        .fields()
+                        .iter()
+                        .map(|field| cell(field.name().clone()))
+                        .join(" | ")
+                ),
+                format!("|{}", "---|".repeat(schema.fields().len())),
+            ];
+            for row in rows {
+                let values = batch
+                    .columns()
+                    .iter()
+                    .map(|column| array_value_to_string(column, row).map(cell))
+                    .collect::<Result<Vec<_>, _>>()?;
+                lines.push(format!("| {} |", values.join(" | ")));
+            }
+            lines.join("\n")
+        }
+        CopyFormat::SqlValues => {
+            let mut values = vec![];
+            for row in rows {
+                let literals = batch
+                    .columns()
+                    .iter()
+                    .map(|column| sql_literal(column, row))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                values.push(format!("  ({})", literals.join(", ")));
+            }
+            format!("VALUES\n{}", values.join(",\n"))
+        }
+        CopyFormat::InList => {
+            if batch.num_columns() != 1 {
+                return Err(anyhow!("Only a single column can be copied as an IN list"));
+            }
+            let column = batch.column(0);
+            let values = rows
+                .filter(|&row| !column.is_null(row))
+                .map(|row| sql_literal(column, row))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            format!("IN ({})", values.into_iter().unique().join(", "))
+        }
+    };
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::arrow::array::{BooleanArray, Int64Array, ListArray};
+    use datafusion::arrow::datatypes::Int64Type;
+
+    use super::*;
+
+    fn batch() -> RecordBatch {
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(Int64Array::from(vec![Some(1), None, Some(1)])),
+            Arc::new(StringArray::from(vec![Some("it's"), Some("b"), None])),
+            Arc::new(BooleanArray::from(vec![Some(true), Some(false), None])),
+        ];
+        let fields = columns
+            .iter()
+            .zip(["id", "name", "flag"])
+            .map(|(column, name)| Field::new(name, column.data_type().clone(), true))
+            .collect_vec();
+        RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).unwrap()
+    }
+
+    fn copy(batch: RecordBatch, format: CopyFormat) -> String {
+        format_selection(batch.schema(), &[batch], format, false).unwrap()
+    }
+
+    #[test]
+    fn cell_range() {
+        let selection = Selection::cell(5, 3).extend_to(2, 1);
+        assert_eq!(selection.rows(), (2, Some(4)));
+        assert_eq!(selection.columns(10), 1..=3);
+        assert!(selection.contains(2, 1));
+        assert!(selection.contains(5, 3));
+        assert!(!selection.contains(6, 3));
+        assert!(!selection.contains(4, 0));
+        assert!(!selection.is_cell());
+        assert!(Selection::cell(5, 3).is_cell());
+    }
+
+    #[test]
+    fn whole_rows() {
+        let selection = Selection::row(7).extend_to(4, 0);
+        assert_eq!(selection.rows(), (4, Some(4)));
+        assert_eq!(selection.columns(3), 0..=2);
+        assert!(selection.contains(4, 100));
+        assert!(!selection.contains(8, 0));
+        assert!(!selection.is_column());
+    }
+
+    #[test]
+    fn whole_columns() {
+        let selection = Selection::column(2).extend_to(0, 4);
+        assert_eq!(selection.rows(), (0, None));
+        assert_eq!(selection.columns(10), 2..=4);
+        assert!(selection.contains(1_000_000, 3));
+        assert!(!selection.contains(0, 1));
+        assert!(Selection::column(2).is_column());
+    }
+
+    #[test]
+    fn sql_values() {
+        assert_eq!(
+            copy(batch(), CopyFormat::SqlValues),
+            "VALUES\n  (1, 'it''s', TRUE),\n  (NULL, 'b', FALSE),\n  (1, NULL, NULL)"
+        );
+    }
+
+    #[test]
+    fn in_list() {
+        let batch = batch();
+        let id = batch.project(&[0]).unwrap();
+        // nulls never match IN, and repeated values are listed once
+        assert_eq!(copy(id, CopyFormat::InList), "IN (1)");
+        let name = batch.project(&[1]).unwrap();
+        assert_eq!(copy(name, CopyFormat::InList), "IN ('it''s', 'b')");
+        assert!(format_selection(batch.schema(), &[batch], CopyFormat::InList, false).is_err());
+    }
+
+    #[test]
+    fn single_cell_is_bare() {
+        let batch = batch().slice(0, 1).project(&[1]).unwrap();
+        assert_eq!(
+            format_selection(batch.schema(), &[batch], CopyFormat::Tsv, true).unwrap(),
+            "it's"
+        );
+    }
+
+    #[test]
+    fn nested_columns_as_text() {
+        let list = ListArray::from_iter_primitive::<Int64Type, _, _>(vec![
+            Some(vec![Some(1), Some(2)]),
+            None,
+        ]);
+        let schema = Schema::new(vec![Field::new("list", list.data_type().clone(), true)]);
+        let batch = RecordBatch::try_new(Arc::new(schema), vec![Arc::new(list)]).unwrap();
+        assert_eq!(copy(batch, CopyFormat::Csv), "list\n\"[1, 2]\"\n\"\"");
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::{BTreeSet, HashMap};
+
+use datafusion::arrow::datatypes::Schema;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+/// How the columns of a result are arranged in its table: their order, which are hidden, and which
+/// are pinned to the left so that they stay in view while scrolling sideways. Columns are named
+/// rather than numbered, so the layout of a table can be kept between sessions, and those it
+/// doesn't mention are shown after the others, in the order of the schema.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct ColumnLayout {
+    pub order: Vec<String>,
+    pub hidden: BTreeSet<String>,
+    pub pinned: BTreeSet<String>,
+}
+
+impl ColumnLayout {
+    /// Indices into `schema` of its columns in the order they're listed, hidden ones included.
+    pub fn ordered(&self, schema: &Schema) -> Vec<usize> {
+        let positions: HashMap<&str, usize> = self
+            .order
+            .iter()
+            .enumerate()
+            .map(|(position, name)| (name.as_str(), position))
+            .collect();
+        // a stable sort, so columns missing from the order keep the order of the schema
+        (0..schema.fields().len())
+            .sorted_by_key(|&index| {
+                positions
+                    .get(schema.field(index).name().as_str())
+                    .copied()
+                    .unwrap_or(positions.len())
+            })
+            .collect()
+    }
+
+    /// Indices into `schema` of the columns shown pinned to the left, then of those that scroll,
+    /// leaving out hidden columns.
+    pub fn visible(&self, schema: &Schema) -> (Vec<usize>, Vec<usize>) {
+        self.ordered(schema)
+            .into_iter()
+            .filter(|&index| !self.is_hidden(schema.field(index).name()))
+            .partition(|&index| self.is_pinned(schema.field(index).name()))
+    }
+
+    /// Move the column listed at `from` to `to`, as positions in [`ColumnLayout::ordered`].
+    pub fn move_column(&mut self, schema: &Schema, from: usize, to: usize) {
+        let mut order = self
+            .ordered(schema)
+            .into_iter()
+            .map(|index| schema.field(index).name().clone())
+            .collect_vec();
+        if from >= order.len() || to >= order.len() {
+            return;
+        }
+        let column = order.remove(from);
+        order.insert(to, column);
+        // columns this result lacks keep their place, for the results that have them
+        order.extend(
+            self.order
+                .iter()
+                .filter(|name| schema.index_of(name).is_err())
+                .cloned(),
+        );
+        self.order = order;
+    }
+
+    pub fn is_hidden(&self, column: &str) -> bool {
+        self.hidden.contains(column)
+    }
+
+    pub fn set_hidden(&mut self, column: &str, hidden: bool) {
+        if hidden {
+            self.hidden.insert(column.to_owned());
+        } else {
+            self.hidden.remove(column);
+        }
+    }
+
+    pub fn is_pinned(&self, column: &str) -> bool {
+        self.pinned.contains(column)
+    }
+
+    pub fn set_pinned(&mut self, column: &str, pinned: bool) {
+        if pinned {
+            self.pinned.insert(column.to_owned());
+        } else {
+            self.pinned.remove(column);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::arrow::datatypes::{DataType, Field};
+
+    use super::*;
+
+    fn schema(names: &[&str]) -> Schema {
+        Schema::new(
+            names
+                .iter()
+                .map(|name| Field::new(*name, DataType::Int64, true))
+                .collect_vec(),
+        )
+    }
+
+    fn layout(order: &[&str]) -> ColumnLayout {
+        ColumnLayout {
+            order: order.iter().map(|name| name.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn ordered_follows_the_schema_by_default() {
+        let schema = schema(&["a", "b", "c"]);
+        assert_eq!(ColumnLayout::default().ordered(&schema), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn ordered_skips_columns_missing_from_the_schema() {
+        let schema = schema(&["a", "b", "c", "d"]);
+        // "x" isn't in this result, and "a" and "d" aren't in the order
+        let columns = layout(&["c", "x", "b"]);
+        assert_eq!(columns.ordered(&schema), vec![2, 1, 0, 3]);
+    }
+
+    #[test]
+    fn move_column_keeps_columns_missing_from_the_schema() {
+        let schema = schema(&["a", "b", "c"]);
+        let mut columns = layout(&["x", "c"]);
+        // listed as c, a, b
+        columns.move_column(&schema, 0, 2);
+        assert_eq!(columns.ordered(&schema), vec![0, 1, 2]);
+        assert_eq!(columns.order, vec!["a", "b", "c", "x"]);
+    }
+
+    #[test]
+    fn move_column_ignores_positions_out_of_range() {
+        let schema = schema(&["a", "b"]);
+        let mut columns = layout(&["b"]);
+        columns.move_column(&schema, 0, 2);
+        columns.move_column(&schema, 5, 0);
+        assert_eq!(columns, layout(&["b"]));
+    }
+
+    #[test]
+    fn visible_splits_pinned_and_leaves_out_hidden() {
+        let schema = schema(&["a", "b", "c", "d"]);
+        let mut columns = layout(&["d", "c", "b", "a"]);
+        columns.set_pinned("b", true);
+        columns.set_hidden("c", true);
+        assert_eq!(columns.visible(&schema), (vec![1], vec![3, 0]));
+        columns.set_hidden("c", false);
+        columns.set_pinned("b", false);
+        assert_eq!(columns.visible(&schema), (vec![], vec![3, 2, 1, 0]));
+    }
+}
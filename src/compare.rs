@@ -0,0 +1,232 @@
+use datafusion::arrow::array::{AsArray, Int64Array};
+use datafusion::arrow::compute::cast;
+use datafusion::arrow::datatypes::{DataType, Schema};
+use datafusion::logical_expr::count;
+use datafusion::prelude::{ident, lit, DataFrame};
+use itertools::Itertools;
+
+use crate::data::quote_identifier;
+
+/// Column of a comparison saying whether a row is only in one table or has changed.
+pub const STATUS_COLUMN: &str = "_diff";
+pub const LEFT_ONLY: &str = "left only";
+pub const RIGHT_ONLY: &str = "right only";
+pub const CHANGED: &str = "changed";
+
+/// Differences between two tables, matching their rows by key columns. Rows that are the same in
+/// both are left out, and each other column is shown from both sides, as `name (left)` and
+/// `name (right)`.
+pub struct Comparison {
+    pub left: String,
+    pub right: String,
+    pub keys: Vec<String>,
+    /// Columns in both tables other than the keys, compared row by row.
+    pub columns: Vec<String>,
+    pub left_only_columns: Vec<String>,
+    pub right_only_columns: Vec<String>,
+    /// Columns in both tables whose types differ, which are compared as text.
+    pub changed_types: Vec<(String, DataType, DataType)>,
+    /// Rows of each status, once they've been counted.
+    pub left_only_rows: usize,
+    pub right_only_rows: usize,
+    pub changed_rows: usize,
+}
+
+pub fn left_column(column: &str) -> String {
+    format!("{} (left)", column)
+}
+
+pub fn right_column(column: &str) -> String {
+    format!("{} (right)", column)
+}
+
+impl Comparison {
+    pub fn new(
+        left: &str,
+        left_schema: &Schema,
+        right: &str,
+        right_schema: &Schema,
+        keys: Vec<String>,
+    ) -> anyhow::Result<Self> {
+        if keys.is_empty() {
+            return Err(anyhow::anyhow!("Pick the columns that identify a row"));
+        }
+        for key in &keys {
+            left_schema.field_with_name(key)?;
+            right_schema.field_with_name(key)?;
+        }
+
+        let mut columns = vec![];
+        let mut left_only_columns = vec![];
+        let mut changed_types = vec![];
+        for field in left_schema.fields() {
+            if keys.contains(field.name()) {
+                continue;
+            }
+            match right_schema.field_with_name(field.name()) {
+                Ok(right_field) => {
+                    if right_field.data_type() != field.data_type() {
+                        changed_types.push((
+                            field.name().clone(),
+                            field.data_type().clone(),
+                            right_field.data_type().clone(),
+                        ));
+                    }
+                    columns.push(field.name().clone());
+                }
+                Err(_) => left_only_columns.push(field.name().clone()),
+            }
+        }
+        let right_only_columns = right_schema
+            .fields()
+            .iter()
+            .map(|field| field.name())
+            .filter(|name| !keys.contains(name) && left_schema.field_with_name(name).is_err())
+            .cloned()
+            .collect();
+
+        Ok(Self {
+            left: left.to_owned(),
+            right: right.to_owned(),
+            keys,
+            columns,
+            left_only_columns,
+            right_only_columns,
+            changed_types,
+            left_only_rows: 0,
+            right_only_rows: 0,
+            changed_rows: 0,
+        })
+    }
+
+    /// Whether the schemas of the two tables differ.
+    pub fn schemas_differ(&self) -> bool {
+        !(self.left_only_columns.is_empty()
+            && self.right_only_columns.is_empty()
+            && self.changed_types.is_empty())
+    }
+
+    /// A full join of the two tables on the keys, keeping the rows that differ.
+    pub fn sql(&self) -> String {
+        let left = |column: &str| format!("l.{}", quote_identifier(column));
+        let right = |column: &str| format!("r.{}", quote_identifier(column));
+        let has_changed_type = |column: &str| {
+            self.changed_types
+                .iter()
+                .any(|(changed, _, _)| changed == column)
+        };
+
+        let mut select = self
+            .keys
+            .iter()
+            .map(|key| {
+                format!(
+                    "coalesce({}, {}) AS {}",
+                    left(key),
+                    right(key),
+                    quote_identifier(key)
+                )
+            })
+            .collect_vec();
+        select.push(format!(
+            "CASE WHEN r.__right IS NULL THEN '{}' WHEN l.__left IS NULL THEN '{}' ELSE '{}' END AS {}",
+            LEFT_ONLY,
+            RIGHT_ONLY,
+            CHANGED,
+            quote_identifier(STATUS_COLUMN)
+        ));
+        for column in &self.columns {
+            select.push(format!(
+                "{} AS {}",
+                left(column),
+                quote_identifier(&left_column(column))
+            ));
+            select.push(format!(
+                "{} AS {}",
+                right(column),
+                quote_identifier(&right_column(column))
+            ));
+        }
+        for column in &self.left_only_columns {
+            select.push(format!(
+                "{} AS {}",
+                left(column),
+                quote_identifier(&left_column(column))
+            ));
+        }
+        for column in &self.right_only_columns {
+            select.push(format!(
+                "{} AS {}",
+                right(column),
+                quote_identifier(&right_column(column))
+            ));
+        }
+
+        let on = self
+            .keys
+            .iter()
+            // so that rows with null keys match, as the other columns are compared
+            .map(|key| format!("{} IS NOT DISTINCT FROM {}", left(key), right(key)))
+            .join(" AND ");
+        let mut differ = vec![
+            "l.__left IS NULL".to_owned(),
+            "r.__right IS NULL".to_owned(),
+        ];
+        differ.extend(self.columns.iter().map(|column| {
+            if has_changed_type(column) {
+                format!(
+                    "(CAST({} AS VARCHAR) IS DISTINCT FROM CAST({} AS VARCHAR))",
+                    left(column),
+                    right(column)
+                )
+            } else {
+                // parenthesized, as IS DISTINCT FROM binds more loosely than OR
+                format!("({} IS DISTINCT FROM {})", left(column), right(column))
+            }
+        }));
+
+        format!(
+            "SELECT {}\nFROM (SELECT *, true AS __left FROM {}) l\nFULL OUTER JOIN (SELECT *, true AS __right FROM {}) r ON {}\nWHERE {}",
+            select.join(", "),
+            quote_identifier(&self.left),
+            quote_identifier(&self.right),
+            on,
+            differ.join(" OR ")
+        )
+    }
+
+    /// The column a cell is compared against, if `column` is one side of a column in both tables.
+    pub fn counterpart(&self, column: &str) -> Option<String> {
+        self.columns.iter().find_map(|name| {
+            if left_column(name) == column {
+                Some(right_column(name))
+            } else if right_column(name) == column {
+                Some(left_column(name))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Count the rows of each status in `df`, the result of [`Comparison::sql`].
+    pub async fn count_rows(&mut self, df: DataFrame) -> anyhow::Result<()> {
+        let batches = df
+            .aggregate(vec![ident(STATUS_COLUMN)], vec![count(lit(1))])?
+            .collect()
+            .await?;
+        for batch in batches {
+            let counts = cast(batch.column(1), &DataType::Int64)?;
+            let counts: &Int64Array = counts.as_primitive();
+            let statuses = batch.column(0).as_string::<i32>();
+            for row in 0..batch.num_rows() {
+                let rows = counts.value(row) as usize;
+                match statuses.value(row) {
+                    LEFT_ONLY => self.left_only_rows = rows,
+                    RIGHT_ONLY => self.right_only_rows = rows,
+                    _ => self.changed_rows = rows,
+                }
+            }
+        }
+        Ok(())
+    }
+}
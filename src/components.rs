@@ -1,20 +1,62 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
-use crate::data::{Data, DataSourceListing, Query, SortState, TableDescriptor};
+use crate::chart::{chart_data, ChartData, ChartKind, ChartSpec};
+use crate::clipboard::{CopyFormat, Selection};
+use crate::columns::ColumnLayout;
+use crate::compare::{self, Comparison};
+use crate::data::{
+    is_parquet, quote_identifier, ColumnFilter, Data, DataSource, DataSourceListing, Filter,
+    ParquetFiles, PartitionType, Query, ResultTable, SortKey, SortState, TableDescriptor,
+    TableFormat,
+};
+use crate::display::{
+    format_value, is_timezone, BinaryFormat, DisplayFormats, EpochUnit, ValueFormat, ValueKind,
+};
+use crate::explain::{PlanNode, QueryPlan};
+use crate::export::{Export, ExportOptions};
+use crate::history::{HistoryEntry, QueryHistory, SavedQuery};
+use crate::merge::FileSchemaDiff;
+use crate::profile::{format_bin, has_histogram, profile_column, ColumnProfile};
+use async_compat::Compat;
+use chrono::{DateTime, Local};
 use datafusion::arrow::{
-    datatypes::{DataType, Schema},
+    datatypes::{DataType, Field, Schema, SchemaRef},
+    json,
+    record_batch::RecordBatch,
     util::display::array_value_to_string,
 };
-use egui::{Context, Id, Response, Ui};
+use datafusion::parquet::basic::{BrotliLevel, Compression, GzipLevel, ZstdLevel};
+use datafusion::parquet::file::properties::DEFAULT_MAX_ROW_GROUP_SIZE;
+use datafusion::parquet::file::statistics::Statistics;
+use datafusion::prelude::DataFrame;
+use datafusion::sql::parser::DFParser;
+use datafusion::sql::sqlparser::dialect::GenericDialect;
+use datafusion::sql::sqlparser::keywords::Keyword;
+use datafusion::sql::sqlparser::parser::Parser;
+use datafusion::sql::sqlparser::tokenizer::{Token, Tokenizer, Whitespace};
+use egui::scroll_area::ScrollBarVisibility;
+use egui::text::{CCursor, CCursorRange, LayoutJob};
+use egui::{Color32, Context, Id, Key, Modifiers, Response, Ui};
 use egui_extras::{Column, TableBuilder};
-use egui_file_dialog::FileDialog;
-use egui_json_tree::JsonTree;
+use egui_file_dialog::{DialogState, FileDialog};
+use egui_json_tree::{DefaultExpand, JsonTree};
+use egui_plot::{Bar, BarChart, Legend, Line, Plot, PlotPoints, Points};
 use itertools::Itertools;
+use regex::Regex;
 use serde_json::Value;
+use smol::Task;
 
 pub type ErrorLog = Vec<anyhow::Error>;
+/// Most suggestions shown at once while typing a query.
+const MAX_COMPLETIONS: usize = 10;
 type FromName = String;
 type ToName = String;
+type Sql = String;
+type Analyze = bool;
+type TableName = String;
 
 pub enum Action {
     AddSource(TableDescriptor),
@@ -22,7 +64,25 @@ pub enum Action {
     LoadSource(TableDescriptor),
     DeleteSource(String),
     RenameSource((FromName, ToName)),
-    SortData((String, SortState)),
+    InspectSource(String),
+    ExportData(Export),
+    // with the SQL of the result, if it can be written as SQL
+    SaveResult((TableName, Box<DataFrame>, ResultTable, Option<String>)),
+    SortData(Vec<SortKey>),
+    FilterData(Vec<Filter>),
+    ProfileColumn(String),
+    ChartColumn(String),
+    ArrangeColumns(ColumnLayout),
+    SaveColumnLayout((TableName, ColumnLayout)),
+    InspectCell(CellInspector),
+    ExpandStruct(String),
+    ExplodeList(String),
+    EditQuery(String),
+    SaveQuery(SavedQuery),
+    ExplainQuery((Sql, Analyze)),
+    CompareTables(Box<Comparison>),
+    OpenWorkspace(PathBuf),
+    SaveWorkspace(PathBuf),
     ShowPopover(Box<dyn Popover>),
     LogError(anyhow::Error),
 }
@@ -40,15 +100,41 @@ pub trait Show {
     fn show(&self, ui: &mut Ui) -> Option<Action>;
 }
 
+/// A suggestion for the word being typed in the query editor.
+struct Completion {
+    text: String,
+    kind: &'static str,
+}
+
+/// A parse error, and the byte offset into the query that it refers to, if known.
+struct SqlError {
+    message: String,
+    offset: Option<usize>,
+}
+
 #[derive(Default)]
 pub struct QueryBuilder {
     query: String,
+    // schemas of the registered tables and the available functions, used for completions
+    tables: BTreeMap<String, SchemaRef>,
+    functions: Vec<String>,
+    // byte range of the word being completed, and the suggestions for it
+    completion_range: Range<usize>,
+    completions: Vec<Completion>,
+    selected_completion: usize,
+    completions_dismissed: bool,
+    // the query text that parse_error was computed for, so it's only parsed on change
+    parsed_query: String,
+    parse_error: Option<SqlError>,
 }
 
 #[derive(Clone, Copy, PartialEq)]
 enum SourceType {
     Azure,
     Local,
+    S3,
+    Gcs,
+    Http,
 }
 
 pub struct AddDataSource {
@@ -57,10 +143,223 @@ pub struct AddDataSource {
     file_dialog: Option<FileDialog>,
     account: String,
     container: String,
+    bucket: String,
+    profile: String,
+    region: String,
+    endpoint: String,
+    credentials: String,
+    url: String,
     path: String,
     extension: String,
+    // None infers the format from the extension
+    format: Option<TableFormat>,
+    delimiter: String,
+    has_header: bool,
+    schema_infer_max_records: usize,
     table_name: String,
     read_metadata: bool,
+    merge_schemas: bool,
+    // declared partition columns, detected from the directories when there are none
+    partition_cols: Vec<(String, PartitionType)>,
+}
+
+pub struct SaveQuery {
+    name: String,
+    folder: String,
+    sql: String,
+}
+
+/// Registers a result as a table, so that later queries can read it.
+pub struct SaveTable {
+    name: String,
+    kind: ResultTable,
+    df: DataFrame,
+    // views are saved in workspaces by their SQL
+    sql: Option<String>,
+}
+
+/// Picks two tables and the columns that match their rows, to list how they differ.
+pub struct CompareTables {
+    tables: BTreeMap<String, SchemaRef>,
+    left: String,
+    right: String,
+    keys: Vec<String>,
+    error: Option<String>,
+}
+
+/// Picks a workspace file to open, or to save the current workspace to.
+pub struct WorkspaceDialog {
+    file_dialog: FileDialog,
+    save: bool,
+}
+
+pub struct ExportData {
+    data: DataFrame,
+    total_rows: Option<usize>,
+    file_dialog: Option<FileDialog>,
+    path: String,
+    format: TableFormat,
+    compression: Compression,
+    row_group_size: usize,
+}
+
+pub struct FileDetails {
+    table_name: String,
+    files: Option<ParquetFiles>,
+    pending: Option<Task<anyhow::Result<ParquetFiles>>>,
+}
+
+/// The plan of a query from the query editor, with the metrics of each operator once analyzed.
+pub struct PlanViewer {
+    sql: String,
+    analyze: bool,
+    plan: Option<QueryPlan>,
+    pending: Option<Task<anyhow::Result<QueryPlan>>>,
+}
+
+/// A chart of columns of a result, shown below the table.
+pub struct ChartPanel {
+    df: DataFrame,
+    spec: ChartSpec,
+    open: bool,
+    chart: Option<ChartData>,
+    error: Option<String>,
+    pending: Option<Task<anyhow::Result<ChartData>>>,
+}
+
+/// Statistics of a column of a result, shown beside the table.
+pub struct ProfilePanel {
+    df: DataFrame,
+    column: String,
+    exact: bool,
+    open: bool,
+    profile: Option<ColumnProfile>,
+    pending: Option<Task<anyhow::Result<ColumnProfile>>>,
+}
+
+impl ProfilePanel {
+    pub fn new(df: DataFrame, column: String) -> Self {
+        let mut panel = Self {
+            df,
+            column,
+            exact: false,
+            open: true,
+            profile: None,
+            pending: None,
+        };
+        panel.run();
+        panel
+    }
+
+    fn run(&mut self) {
+        let df = self.df.clone();
+        let column = self.column.clone();
+        let exact = self.exact;
+        self.pending = Some(smol::spawn(Compat::new(async move {
+            profile_column(df, column, exact).await
+        })));
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+}
+
+impl PlanViewer {
+    pub fn new(sql: String, analyze: bool, pending: Task<anyhow::Result<QueryPlan>>) -> Self {
+        Self {
+            sql,
+            analyze,
+            plan: None,
+            pending: Some(pending),
+        }
+    }
+}
+
+impl ChartPanel {
+    /// A histogram of `x` if it has numbers, dates or times, otherwise a count of each value.
+    pub fn new(df: DataFrame, x: String) -> Self {
+        let binned = df
+            .schema()
+            .field_with_unqualified_name(&x)
+            .is_ok_and(|field| has_histogram(field.data_type()));
+        let mut panel = Self {
+            df,
+            spec: ChartSpec {
+                kind: if binned {
+                    ChartKind::Histogram
+                } else {
+                    ChartKind::Bar
+                },
+                x,
+                y: None,
+                group: None,
+            },
+            open: true,
+            chart: None,
+            error: None,
+            pending: None,
+        };
+        panel.run();
+        panel
+    }
+
+    fn run(&mut self) {
+        let df = self.df.clone();
+        let spec = self.spec.clone();
+        self.error = None;
+        self.pending = Some(smol::spawn(Compat::new(async move {
+            chart_data(df, spec).await
+        })));
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+}
+
+/// A single nested value, shown as a tree.
+pub struct CellInspector {
+    id: Id,
+    column: String,
+    data_type: DataType,
+    row: usize,
+    value: Value,
+}
+
+impl CellInspector {
+    pub fn new(id: Id, field: &Field, row: usize, batch: &RecordBatch, offset: usize) -> Self {
+        // the json writer handles every nested type, so reuse it rather than walking the array
+        let value = (|| -> anyhow::Result<Value> {
+            let column = batch
+                .project(&[batch.schema().index_of(field.name())?])?
+                .slice(offset, 1);
+            let mut writer = json::ArrayWriter::new(vec![]);
+            writer.write(&column)?;
+            writer.finish()?;
+            let mut rows: Value = serde_json::from_slice(&writer.into_inner())?;
+            Ok(rows[0][field.name()].take())
+        })()
+        .unwrap_or_else(|err| Value::String(format!("Could not read value: {}", err)));
+
+        Self {
+            id,
+            column: field.name().clone(),
+            data_type: field.data_type().clone(),
+            row,
+            value,
+        }
+    }
+}
+
+impl FileDetails {
+    pub fn new(table_name: String, pending: Task<anyhow::Result<ParquetFiles>>) -> Self {
+        Self {
+            table_name,
+            files: None,
+            pending: Some(pending),
+        }
+    }
 }
 
 impl Popover for ErrorLog {
@@ -93,15 +392,50 @@ impl Default for AddDataSource {
             file_dialog: None,
             account: "".to_owned(),
             container: "".to_owned(),
+            bucket: "".to_owned(),
+            profile: "".to_owned(),
+            region: "".to_owned(),
+            endpoint: "".to_owned(),
+            credentials: "".to_owned(),
+            url: "".to_owned(),
             path: "".to_owned(),
             extension: "".to_owned(),
+            format: None,
+            delimiter: "".to_owned(),
+            has_header: true,
+            schema_infer_max_records: 1000,
             table_name: "".to_owned(),
             read_metadata: true,
+            merge_schemas: false,
+            partition_cols: vec![],
         }
     }
 }
 
 impl AddDataSource {
+    /// The path or URL the source is read from, which its format is inferred from.
+    fn location(&self) -> &str {
+        match self.source_type {
+            SourceType::Http => &self.url,
+            _ => &self.path,
+        }
+    }
+
+    fn format(&self) -> Option<TableFormat> {
+        let extension = if self.extension.is_empty() {
+            self.location().rsplit('.').next().unwrap_or_default()
+        } else {
+            &self.extension
+        };
+        self.format.or(TableFormat::from_extension(extension))
+    }
+
+    /// Unknown formats are read as parquet.
+    fn can_merge_schemas(&self) -> bool {
+        self.format()
+            .map_or(true, |format| format.can_merge_schemas())
+    }
+
     fn build(&self) -> anyhow::Result<TableDescriptor> {
         let mut table = match self.source_type {
             SourceType::Azure => {
@@ -109,11 +443,59 @@ impl AddDataSource {
                     .with_account(&self.account)
             }
             SourceType::Local => TableDescriptor::new(&self.path)?,
+            SourceType::S3 => {
+                let mut table =
+                    TableDescriptor::new(&format!("s3://{}/{}", self.bucket, self.path))?;
+                if !self.profile.is_empty() {
+                    table = table.with_profile(&self.profile);
+                }
+                if !self.region.is_empty() {
+                    table = table.with_region(&self.region);
+                }
+                if !self.endpoint.is_empty() {
+                    table = table.with_endpoint(&self.endpoint);
+                }
+                table
+            }
+            SourceType::Gcs => {
+                let table = TableDescriptor::new(&format!("gs://{}/{}", self.bucket, self.path))?;
+                if self.credentials.is_empty() {
+                    table
+                } else {
+                    table.with_credentials(&self.credentials)
+                }
+            }
+            SourceType::Http => TableDescriptor::new(&self.url)?,
         };
         if !self.extension.is_empty() {
             table = table.with_extension(&self.extension);
         }
-        table = table.with_load_metadata(self.read_metadata);
+        if let Some(format) = self.format {
+            table = table.with_format(format);
+        }
+        match self.delimiter.as_str() {
+            "" => {}
+            r"\t" => table = table.with_delimiter(b'\t'),
+            delimiter => match delimiter.as_bytes() {
+                [byte] if byte.is_ascii() => table = table.with_delimiter(*byte),
+                _ => anyhow::bail!("Delimiter must be a single ASCII character: {delimiter}"),
+            },
+        }
+        table = table
+            .with_header(self.has_header)
+            .with_schema_infer_max_records(self.schema_infer_max_records);
+        table = table
+            .with_load_metadata(self.read_metadata)
+            .with_merge_schemas(self.merge_schemas && self.can_merge_schemas());
+        let partition_cols = self
+            .partition_cols
+            .iter()
+            .filter(|(name, _)| !name.trim().is_empty())
+            .map(|(name, partition_type)| (name.trim().to_owned(), *partition_type))
+            .collect_vec();
+        if !partition_cols.is_empty() {
+            table = table.with_partition_cols(partition_cols);
+        }
         if !self.table_name.is_empty() {
             table = table.with_table_name(&self.table_name);
         }
@@ -135,6 +517,9 @@ impl Popover for AddDataSource {
                         ui.scope(|ui| {
                             ui.selectable_value(&mut self.source_type, SourceType::Local, "Local");
                             ui.selectable_value(&mut self.source_type, SourceType::Azure, "Azure");
+                            ui.selectable_value(&mut self.source_type, SourceType::S3, "S3");
+                            ui.selectable_value(&mut self.source_type, SourceType::Gcs, "GCS");
+                            ui.selectable_value(&mut self.source_type, SourceType::Http, "HTTP");
                         });
 
                         ui.checkbox(&mut self.read_metadata, "Read Metadata");
@@ -147,6 +532,89 @@ impl Popover for AddDataSource {
                         ui.label("Extension");
                         ui.text_edit_singleline(&mut self.extension);
                         ui.end_row();
+
+                        ui.label("Format");
+                        egui::ComboBox::from_id_source("Add Data Source Format")
+                            .selected_text(self.format.map_or("Infer", |f| f.name()))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut self.format, None, "Infer");
+                                for format in TableFormat::ALL {
+                                    ui.selectable_value(&mut self.format, Some(format), format.name());
+                                }
+                            });
+                        ui.end_row();
+
+                        if let Some(TableFormat::Csv) = self.format() {
+                            ui.label("Delimiter");
+                            ui.text_edit_singleline(&mut self.delimiter);
+                            ui.end_row();
+
+                            ui.label("Header Row");
+                            ui.checkbox(&mut self.has_header, "");
+                            ui.end_row();
+                        }
+                        if let Some(TableFormat::Csv | TableFormat::Json) = self.format() {
+                            ui.label("Schema Inference Rows");
+                            ui.add(egui::DragValue::new(&mut self.schema_infer_max_records));
+                            ui.end_row();
+                        }
+
+                        if self.can_merge_schemas() {
+                            ui.label("Merge Schemas");
+                            ui.checkbox(&mut self.merge_schemas, "").on_hover_text(
+                                "Combine files with different columns, reading missing columns as nulls and widening types",
+                            );
+                            ui.end_row();
+                        }
+
+                        ui.label("Partitions");
+                        ui.vertical(|ui| {
+                            let mut remove = None;
+                            for (i, (name, partition_type)) in
+                                self.partition_cols.iter_mut().enumerate()
+                            {
+                                ui.horizontal(|ui| {
+                                    ui.add(
+                                        egui::TextEdit::singleline(name)
+                                            .hint_text("Column")
+                                            .desired_width(
+                                                ui.style().spacing.text_edit_width / 2.0,
+                                            ),
+                                    );
+                                    egui::ComboBox::from_id_source((
+                                        "Add Data Source Partition Type",
+                                        i,
+                                    ))
+                                    .selected_text(partition_type.name())
+                                    .show_ui(ui, |ui| {
+                                        for option in PartitionType::ALL {
+                                            ui.selectable_value(
+                                                partition_type,
+                                                option,
+                                                option.name(),
+                                            );
+                                        }
+                                    });
+                                    if ui.small_button("✖").clicked() {
+                                        remove = Some(i);
+                                    }
+                                });
+                            }
+                            if let Some(i) = remove {
+                                self.partition_cols.remove(i);
+                            }
+                            if ui
+                                .small_button("Add")
+                                .on_hover_text(
+                                    "Without declared columns, key=value directories are detected as strings",
+                                )
+                                .clicked()
+                            {
+                                self.partition_cols
+                                    .push(("".to_owned(), PartitionType::Utf8));
+                            }
+                        });
+                        ui.end_row();
                         match self.source_type {
                             SourceType::Local => {
                                 ui.label("Path");
@@ -180,24 +648,77 @@ impl Popover for AddDataSource {
                                 ui.text_edit_singleline(&mut self.path);
                                 ui.end_row();
                             }
+                            SourceType::S3 => {
+                                ui.label("Bucket");
+                                ui.text_edit_singleline(&mut self.bucket);
+                                ui.end_row();
+
+                                ui.label("Path");
+                                ui.text_edit_singleline(&mut self.path);
+                                ui.end_row();
+
+                                ui.label("Profile");
+                                ui.text_edit_singleline(&mut self.profile);
+                                ui.end_row();
+
+                                ui.label("Region");
+                                ui.text_edit_singleline(&mut self.region);
+                                ui.end_row();
+
+                                ui.label("Endpoint");
+                                ui.text_edit_singleline(&mut self.endpoint);
+                                ui.end_row();
+                            }
+                            SourceType::Gcs => {
+                                ui.label("Bucket");
+                                ui.text_edit_singleline(&mut self.bucket);
+                                ui.end_row();
+
+                                ui.label("Path");
+                                ui.text_edit_singleline(&mut self.path);
+                                ui.end_row();
+
+                                ui.label("Credentials");
+                                ui.text_edit_singleline(&mut self.credentials);
+                                ui.end_row();
+                            }
+                            SourceType::Http => {
+                                ui.label("URL");
+                                ui.text_edit_singleline(&mut self.url);
+                                ui.end_row();
+                            }
                         }
                         ui.end_row();
                     });
-                if let SourceType::Azure = self.source_type {
-                    ui.label("Requires the azure cli to be installed and available on PATH");
+                match self.source_type {
+                    SourceType::Azure => {
+                        ui.label("Requires the azure cli to be installed and available on PATH");
+                    }
+                    SourceType::S3 => {
+                        ui.label("Credentials are read from AWS_* variables or the named profile");
+                    }
+                    SourceType::Gcs => {
+                        ui.label("Credentials file is optional, gcloud application defaults are used otherwise");
+                    }
+                    SourceType::Http => {
+                        ui.label("HTTP sources are read-only, directories require a WebDAV server");
+                    }
+                    SourceType::Local => {}
                 }
 
                 ui.add_space(ui.style().spacing.interact_size.y);
                 ui.vertical_centered_justified(|ui| {
                     if ui.button("add").clicked() {
-                        if let Ok(table) = self.build() {
-                            action = Some(Action::AddSource(table));
-                        }
+                        action = Some(match self.build() {
+                            Ok(table) => Action::AddSource(table),
+                            Err(error) => Action::LogError(error),
+                        });
                     }
                     if ui.button("load").clicked() {
-                        if let Ok(table) = self.build() {
-                            action = Some(Action::LoadSource(table));
-                        }
+                        action = Some(match self.build() {
+                            Ok(table) => Action::LoadSource(table),
+                            Err(error) => Action::LogError(error),
+                        });
                     }
                 });
             });
@@ -206,146 +727,2553 @@ impl Popover for AddDataSource {
     }
 }
 
-impl ShowMut for QueryBuilder {
-    fn show(&mut self, ui: &mut Ui) -> Option<Action> {
-        egui::TextEdit::multiline(&mut self.query)
-            .clip_text(true)
-            .show(ui);
-        let submit = ui.button("Query");
-        if submit.clicked() {
-            Some(Action::QuerySource(Query::Sql(self.query.to_owned())))
-        } else {
-            None
+impl SaveQuery {
+    pub fn new(sql: String) -> Self {
+        Self {
+            name: "".to_owned(),
+            folder: "".to_owned(),
+            sql,
         }
     }
 }
 
-impl Show for Data {
-    fn show(&self, ui: &mut Ui) -> Option<Action> {
-        let style = &ui.style().clone();
+impl Popover for SaveQuery {
+    fn popover(&mut self, ctx: &Context) -> (bool, Option<Action>) {
+        let mut open = true;
+        let mut action = None;
+        egui::Window::new("Save Query")
+            .collapsible(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                egui::Grid::new("Save Query").num_columns(2).show(ui, |ui| {
+                    ui.label("Name");
+                    ui.text_edit_singleline(&mut self.name);
+                    ui.end_row();
 
-        fn get_sort_state(sort_state: &Option<(String, SortState)>, col: &str) -> SortState {
-            match sort_state {
-                Some((sorted_col, sort_state)) => {
-                    if *sorted_col == col {
-                        sort_state.to_owned()
-                    } else {
-                        SortState::NotSorted
-                    }
+                    ui.label("Folder");
+                    ui.add(egui::TextEdit::singleline(&mut self.folder).hint_text("None"));
+                    ui.end_row();
+                });
+                ui.label(egui::RichText::new(&self.sql).monospace());
+
+                let name = self.name.trim();
+                if ui
+                    .add_enabled(!name.is_empty(), egui::Button::new("Save"))
+                    .clicked()
+                {
+                    action = Some(Action::SaveQuery(SavedQuery {
+                        name: name.to_owned(),
+                        folder: self.folder.trim().to_owned(),
+                        sql: self.sql.clone(),
+                    }));
                 }
-                _ => SortState::NotSorted,
-            }
-        }
+            });
+        (open && action.is_none(), action)
+    }
+}
 
-        let text_height = egui::TextStyle::Body.resolve(style).size;
-        // stop columns from getting too small to be usable
-        let min_col_width = style.spacing.text_edit_width / 2f32;
-        let margin_width = 2.0f32; // default separator width, keeps column resize on screen
+impl SaveTable {
+    pub fn new(df: DataFrame, sql: Option<String>) -> Self {
+        Self {
+            name: "".to_owned(),
+            kind: ResultTable::View,
+            df,
+            sql,
+        }
+    }
+}
 
-        // we put buttons in the header, so make sure that the vertical size of the header includes
-        // the button size and the normal padding around buttons
-        let header_height = style.spacing.interact_size.y + (2.0f32 * style.spacing.item_spacing.y);
-        let mut action: Option<Action> = None;
+impl Popover for SaveTable {
+    fn popover(&mut self, ctx: &Context) -> (bool, Option<Action>) {
+        let mut open = true;
+        let mut action = None;
+        egui::Window::new("Save as Table")
+            .collapsible(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                egui::Grid::new("Save as Table")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Name");
+                        ui.text_edit_singleline(&mut self.name);
+                        ui.end_row();
 
-        TableBuilder::new(ui)
-            .striped(true)
-            .auto_shrink(false)
-            .max_scroll_height(f32::INFINITY)
-            .columns(
-                Column::auto()
-                    .at_least(min_col_width)
-                    .clip(true)
-                    .resizable(true),
-                self.data.num_columns(),
-            )
-            .column(
-                Column::remainder()
-                    .at_least(margin_width)
-                    .resizable(false)
-                    .clip(false),
-            )
-            .resizable(true)
-            .header(header_height, |mut header| {
-                for field in self.data.schema().fields() {
-                    header.col(|ui| {
-                        let column_name = field.name().to_string();
-                        let mut sort_state = get_sort_state(&self.sort_state, &column_name);
-                        ui.horizontal_centered(|ui| {
-                            let response = ui.multi_state_button(&mut sort_state, &column_name);
-                            if response.clicked() {
-                                action = Some(Action::SortData((column_name.clone(), sort_state)));
-                            }
+                        ui.label("Keep as");
+                        ui.horizontal(|ui| {
+                            ui.radio_value(&mut self.kind, ResultTable::View, "View")
+                                .on_hover_text("Run the query again each time the table is read");
+                            ui.radio_value(&mut self.kind, ResultTable::Snapshot, "Snapshot")
+                                .on_hover_text("Keep the current rows in memory");
                         });
+                        ui.end_row();
                     });
+                match (self.kind, &self.sql) {
+                    (ResultTable::View, Some(_)) => {}
+                    (ResultTable::View, None) => {
+                        ui.weak("Expanded and exploded columns can't be saved in a workspace, so this view is kept for this session only");
+                    }
+                    (ResultTable::Snapshot, _) => {
+                        ui.weak("Snapshots are kept for this session only, and aren't saved in a workspace");
+                    }
                 }
-            })
-            .body(|body| {
-                body.rows(text_height, self.data.num_rows(), |mut row| {
-                    for data_col in self.data.columns() {
-                        let index = row.index();
-                        row.col(|ui| {
-                            // while not efficient (as noted in docs) we need to display
-                            // at most a few dozen records at a time (barring pathological
-                            // tables with absurd numbers of columns) and should still
-                            // have conversion times on the order of ns.
-                            // TODO: have separate value layout function
-                            ui.with_layout(
-                                if is_integer(data_col.data_type()) {
-                                    egui::Layout::centered_and_justified(
-                                        egui::Direction::LeftToRight,
-                                    )
-                                } else if is_float(data_col.data_type()) {
-                                    egui::Layout::right_to_left(egui::Align::Center)
+
+                let name = self.name.trim();
+                if ui
+                    .add_enabled(!name.is_empty(), egui::Button::new("Save"))
+                    .clicked()
+                {
+                    action = Some(Action::SaveResult((
+                        name.to_owned(),
+                        Box::new(self.df.clone()),
+                        self.kind,
+                        self.sql.clone(),
+                    )));
+                }
+            });
+        (open && action.is_none(), action)
+    }
+}
+
+impl CompareTables {
+    pub fn new(tables: &DataSourceListing) -> Self {
+        let tables: BTreeMap<_, _> = tables
+            .iter()
+            .map(|(name, table)| (name.clone(), table.schema()))
+            .collect();
+        let mut names = tables.keys();
+        let left = names.next().cloned().unwrap_or_default();
+        let right = names.next().cloned().unwrap_or_else(|| left.clone());
+        Self {
+            tables,
+            left,
+            right,
+            keys: vec![],
+            error: None,
+        }
+    }
+
+    /// Columns of the left table that the right table also has.
+    fn shared_columns(&self) -> Vec<String> {
+        match (self.tables.get(&self.left), self.tables.get(&self.right)) {
+            (Some(left), Some(right)) => left
+                .fields()
+                .iter()
+                .map(|field| field.name())
+                .filter(|name| right.field_with_name(name).is_ok())
+                .cloned()
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+impl Popover for CompareTables {
+    fn popover(&mut self, ctx: &Context) -> (bool, Option<Action>) {
+        let mut open = true;
+        let mut action = None;
+        egui::Window::new("Compare Tables")
+            .collapsible(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                egui::Grid::new("Compare Tables")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        for (label, table) in [("Left", &mut self.left), ("Right", &mut self.right)]
+                        {
+                            ui.label(label);
+                            egui::ComboBox::from_id_source(("Compare Tables", label))
+                                .selected_text(table.as_str())
+                                .show_ui(ui, |ui| {
+                                    for name in self.tables.keys() {
+                                        ui.selectable_value(table, name.clone(), name);
+                                    }
+                                });
+                            ui.end_row();
+                        }
+                    });
+
+                let shared = self.shared_columns();
+                self.keys.retain(|key| shared.contains(key));
+                ui.label("Match rows on");
+                egui::ScrollArea::vertical()
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        for column in &shared {
+                            let mut checked = self.keys.contains(column);
+                            if ui.checkbox(&mut checked, column).changed() {
+                                if checked {
+                                    self.keys.push(column.clone());
                                 } else {
-                                    egui::Layout::left_to_right(egui::Align::Center)
+                                    self.keys.retain(|key| key != column);
                                 }
-                                .with_main_wrap(false),
-                                |ui| {
-                                    let value = array_value_to_string(data_col, index).unwrap();
-                                    ui.label(value);
-                                },
-                            );
-                        });
-                    }
-                    row.col(|_ui| {
-                        // make last empty column to take up remaining space
+                            }
+                        }
                     });
-                });
+
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.style().visuals.error_fg_color, error);
+                }
+                if ui
+                    .add_enabled(!self.keys.is_empty(), egui::Button::new("Compare"))
+                    .clicked()
+                {
+                    let (Some(left), Some(right)) =
+                        (self.tables.get(&self.left), self.tables.get(&self.right))
+                    else {
+                        return;
+                    };
+                    match Comparison::new(&self.left, left, &self.right, right, self.keys.clone()) {
+                        Ok(comparison) => {
+                            action = Some(Action::CompareTables(Box::new(comparison)))
+                        }
+                        Err(err) => self.error = Some(err.to_string()),
+                    }
+                }
             });
-        action
+        (open && action.is_none(), action)
     }
 }
 
-impl Show for Schema {
-    fn show(&self, ui: &mut Ui) -> Option<Action> {
-        ui.collapsing("Schema", |ui| {
-            for field in self.fields.iter() {
-                ui.label(format!("{}: {}", field.name(), field.data_type()));
-            }
-        });
-        ui.collapsing("Metadata", |ui| {
-            for (key, value) in self.metadata.iter() {
-                if let Ok(json) = serde_json::from_str::<Value>(value) {
-                    JsonTree::new(key, &json).show(ui);
-                } else {
-                    ui.label(format!("{}: {}", key, value));
-                }
-            }
-        });
-        None
+impl WorkspaceDialog {
+    pub fn open() -> Self {
+        let mut file_dialog = FileDialog::new().title("Open Workspace");
+        file_dialog.select_file();
+        Self {
+            file_dialog,
+            save: false,
+        }
+    }
+
+    pub fn save() -> Self {
+        let mut file_dialog = FileDialog::new()
+            .title("Save Workspace")
+            .default_file_name("workspace.toml");
+        file_dialog.save_file();
+        Self {
+            file_dialog,
+            save: true,
+        }
     }
 }
 
-trait EditableLabel {
-    fn editable_label(&mut self, id: Id, label: &str) -> Option<String>;
+impl Popover for WorkspaceDialog {
+    fn popover(&mut self, ctx: &Context) -> (bool, Option<Action>) {
+        self.file_dialog.update(ctx);
+        match self.file_dialog.take_selected() {
+            Some(path) if self.save => (false, Some(Action::SaveWorkspace(path))),
+            Some(path) => (false, Some(Action::OpenWorkspace(path))),
+            None => (self.file_dialog.state() == DialogState::Open, None),
+        }
+    }
 }
 
-impl EditableLabel for Ui {
-    fn editable_label(&mut self, id: Id, label: &str) -> Option<String> {
-        type State = Arc<Mutex<String>>;
+impl ExportData {
+    pub fn new(data: DataFrame, total_rows: Option<usize>) -> Self {
+        ExportData {
+            data,
+            total_rows,
+            file_dialog: None,
+            path: "".to_owned(),
+            format: TableFormat::Parquet,
+            compression: Compression::ZSTD(ZstdLevel::default()),
+            row_group_size: DEFAULT_MAX_ROW_GROUP_SIZE,
+        }
+    }
+}
 
-        let current_label: Option<State> = self.memory_mut(|mem| mem.data.get_temp(id).clone());
-        match current_label.as_ref() {
+impl Popover for ExportData {
+    fn popover(&mut self, ctx: &Context) -> (bool, Option<Action>) {
+        let mut open = true;
+        let mut exported = false;
+        let mut action: Option<Action> = None;
+        egui::Window::new("Export Data")
+            .collapsible(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                egui::Grid::new("Export Data")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Format");
+                        ui.horizontal(|ui| {
+                            for format in [
+                                TableFormat::Parquet,
+                                TableFormat::Csv,
+                                TableFormat::Json,
+                                TableFormat::Arrow,
+                            ] {
+                                ui.selectable_value(&mut self.format, format, format.name());
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Path");
+                        ui.horizontal(|ui| {
+                            ui.text_edit_singleline(&mut self.path);
+                            if ui.button("Browse...").clicked() {
+                                let dialog = self
+                                    .file_dialog
+                                    .get_or_insert(FileDialog::new().show_path_edit_button(true));
+                                dialog.save_file();
+                            };
+                        });
+                        ui.end_row();
+
+                        if let Some(dialog) = self.file_dialog.as_mut() {
+                            dialog.update(ctx);
+                            if let Some(path) = dialog.take_selected() {
+                                self.path = path.to_string_lossy().into_owned();
+                            };
+                        }
+
+                        if let TableFormat::Parquet = self.format {
+                            ui.label("Compression");
+                            egui::ComboBox::from_id_source("Export Data Compression")
+                                .selected_text(format!("{}", self.compression))
+                                .show_ui(ui, |ui| {
+                                    for compression in [
+                                        Compression::UNCOMPRESSED,
+                                        Compression::SNAPPY,
+                                        Compression::GZIP(GzipLevel::default()),
+                                        Compression::LZ4_RAW,
+                                        Compression::ZSTD(ZstdLevel::default()),
+                                        Compression::BROTLI(BrotliLevel::default()),
+                                    ] {
+                                        ui.selectable_value(
+                                            &mut self.compression,
+                                            compression,
+                                            format!("{}", compression),
+                                        );
+                                    }
+                                });
+                            ui.end_row();
+
+                            ui.label("Row Group Size");
+                            ui.add(
+                                egui::DragValue::new(&mut self.row_group_size)
+                                    .clamp_range(1..=usize::MAX),
+                            );
+                            ui.end_row();
+                        }
+                    });
+                ui.label("Paths may be local, or a url in any store already used by a source");
+
+                ui.add_space(ui.style().spacing.interact_size.y);
+                ui.vertical_centered_justified(|ui| {
+                    if ui.button("export").clicked() && !self.path.is_empty() {
+                        let options = ExportOptions {
+                            path: self.path.clone(),
+                            format: self.format,
+                            compression: self.compression,
+                            row_group_size: self.row_group_size,
+                        };
+                        action = Some(Action::ExportData(Export::new(
+                            self.data.clone(),
+                            self.total_rows,
+                            options,
+                        )));
+                        exported = true;
+                    }
+                });
+            });
+
+        (open && !exported, action)
+    }
+}
+
+impl Popover for FileDetails {
+    fn popover(&mut self, ctx: &Context) -> (bool, Option<Action>) {
+        let mut open = true;
+        let mut action = None;
+
+        if self.pending.as_ref().is_some_and(|task| task.is_finished()) {
+            let task = self.pending.take().expect("Checked above");
+            match smol::block_on(task) {
+                Ok(files) => self.files = Some(files),
+                Err(err) => {
+                    action = Some(Action::LogError(err));
+                    open = false;
+                }
+            }
+        }
+
+        egui::Window::new(format!("File Details: {}", self.table_name))
+            .collapsible(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                egui::ScrollArea::both()
+                    .auto_shrink(false)
+                    .show(ui, |ui| match &self.files {
+                        Some(files) => {
+                            files.show(ui);
+                        }
+                        None => {
+                            ui.ctx().request_repaint();
+                            ui.centered_and_justified(|ui| ui.spinner());
+                        }
+                    });
+            });
+
+        (open, action)
+    }
+}
+
+/// The value of a metric of a plan operator, if it was recorded.
+fn plan_metric<'a>(node: &'a PlanNode, name: &str) -> Option<&'a str> {
+    node.metrics
+        .iter()
+        .find(|(metric, _)| metric == name)
+        .map(|(_, value)| value.as_str())
+}
+
+/// An operator as a collapsible header, with its details, metrics and inputs inside.
+fn show_plan_node(ui: &mut Ui, node: &PlanNode, id: Id) {
+    let mut header = vec![node.operator.clone()];
+    if let Some(rows) = plan_metric(node, "output_rows") {
+        header.push(format!("{} rows", rows));
+    }
+    if let Some(elapsed) = plan_metric(node, "elapsed_compute") {
+        header.push(elapsed.to_owned());
+    }
+    egui::CollapsingHeader::new(egui::RichText::new(header.join(" · ")).strong())
+        .id_source(id)
+        .default_open(true)
+        .show(ui, |ui| {
+            if !node.details.is_empty() {
+                ui.add(egui::Label::new(egui::RichText::new(&node.details).monospace()).wrap(true));
+            }
+            if !node.metrics.is_empty() {
+                egui::Grid::new(id.with("metrics"))
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (name, value) in &node.metrics {
+                            ui.label(name);
+                            ui.label(value);
+                            ui.end_row();
+                        }
+                    });
+            }
+            for (i, child) in node.children.iter().enumerate() {
+                show_plan_node(ui, child, id.with(i));
+            }
+        });
+}
+
+impl Popover for PlanViewer {
+    fn popover(&mut self, ctx: &Context) -> (bool, Option<Action>) {
+        let mut open = true;
+        let mut action = None;
+
+        if self.pending.as_ref().is_some_and(|task| task.is_finished()) {
+            let task = self.pending.take().expect("Checked above");
+            match smol::block_on(task) {
+                Ok(plan) => self.plan = Some(plan),
+                Err(err) => {
+                    action = Some(Action::LogError(err));
+                    open = false;
+                }
+            }
+        }
+
+        let title = if self.analyze {
+            "Explain Analyze"
+        } else {
+            "Explain"
+        };
+        egui::Window::new(title)
+            .collapsible(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    match self.plan.as_ref().and_then(|plan| plan.elapsed) {
+                        Some(elapsed) => {
+                            ui.label(format!("Ran in {:.2?}", elapsed));
+                        }
+                        None if self.pending.is_some() => {
+                            ui.ctx().request_repaint();
+                            ui.spinner();
+                            ui.label(if self.analyze {
+                                "running query"
+                            } else {
+                                "planning query"
+                            });
+                        }
+                        None => {}
+                    }
+                    if !self.analyze
+                        && ui
+                            .button("Analyze")
+                            .on_hover_text("Run the query to measure each operator")
+                            .clicked()
+                    {
+                        action = Some(Action::ExplainQuery((self.sql.clone(), true)));
+                    }
+                });
+                ui.separator();
+                egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                    if let Some(plan) = &self.plan {
+                        egui::CollapsingHeader::new("Logical plan")
+                            .default_open(false)
+                            .show(ui, |ui| {
+                                show_plan_node(ui, &plan.logical, Id::new("logical_plan"));
+                            });
+                        egui::CollapsingHeader::new("Physical plan")
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                show_plan_node(ui, &plan.physical, Id::new("physical_plan"));
+                            });
+                    }
+                });
+            });
+
+        (open, action)
+    }
+}
+
+/// Picks a column of `fields` for a chart, or none if `none` is given.
+fn column_picker(
+    ui: &mut Ui,
+    label: &str,
+    column: &mut Option<String>,
+    fields: &[&Field],
+    none: Option<&str>,
+) {
+    ui.label(label);
+    egui::ComboBox::from_id_source(("chart", label))
+        .selected_text(column.as_deref().or(none).unwrap_or_default())
+        .show_ui(ui, |ui| {
+            if let Some(none) = none {
+                ui.selectable_value(column, None, none);
+            }
+            for field in fields {
+                ui.selectable_value(column, Some(field.name().clone()), field.name());
+            }
+        });
+}
+
+impl Show for ChartData {
+    fn show(&self, ui: &mut Ui) -> Option<Action> {
+        let x_type = self.x_type.clone();
+        let categories = self.categories.clone();
+        let kind = self.spec.kind;
+        Plot::new("chart")
+            .legend(Legend::default())
+            .x_axis_formatter(move |mark, _, _| match kind {
+                ChartKind::Bar => {
+                    // only whole positions are bars
+                    let position = mark.value.round();
+                    if (mark.value - position).abs() < f64::EPSILON && position >= 0.0 {
+                        categories
+                            .get(position as usize)
+                            .cloned()
+                            .unwrap_or_default()
+                    } else {
+                        "".to_owned()
+                    }
+                }
+                _ => format_bin(mark.value, &x_type),
+            })
+            .show(ui, |plot| {
+                let groups = self.series.len().max(1) as f64;
+                for (i, series) in self.series.iter().enumerate() {
+                    match kind {
+                        ChartKind::Line => plot.line(
+                            Line::new(PlotPoints::from(series.points.clone())).name(&series.name),
+                        ),
+                        ChartKind::Scatter => plot.points(
+                            Points::new(series.points.clone())
+                                .radius(2.0)
+                                .name(&series.name),
+                        ),
+                        ChartKind::Bar => {
+                            // the bars of each group sit side by side within their category
+                            let width = 0.8 / groups;
+                            let offset = width * (i as f64 + 0.5) - 0.4;
+                            let bars = series
+                                .points
+                                .iter()
+                                .map(|[x, y]| Bar::new(x + offset, *y).width(width))
+                                .collect();
+                            plot.bar_chart(BarChart::new(bars).name(&series.name));
+                        }
+                        ChartKind::Histogram => {
+                            let bars = series
+                                .points
+                                .iter()
+                                .map(|[start, count]| {
+                                    Bar::new(start + self.bin_width / 2.0, *count)
+                                        .width(self.bin_width)
+                                })
+                                .collect();
+                            plot.bar_chart(BarChart::new(bars).name(&series.name));
+                        }
+                    }
+                }
+            });
+        None
+    }
+}
+
+impl ShowMut for ChartPanel {
+    fn show(&mut self, ui: &mut Ui) -> Option<Action> {
+        if self.pending.as_ref().is_some_and(|task| task.is_finished()) {
+            let task = self.pending.take().expect("Checked above");
+            match smol::block_on(task) {
+                Ok(chart) => self.chart = Some(chart),
+                Err(err) => self.error = Some(err.to_string()),
+            }
+        }
+
+        let schema = self.df.schema().as_arrow().clone();
+        let fields = schema.fields().iter().map(AsRef::as_ref).collect_vec();
+        let numeric = fields
+            .iter()
+            .copied()
+            .filter(|field| field.data_type().is_numeric())
+            .collect_vec();
+        let spec = self.spec.clone();
+        ui.horizontal(|ui| {
+            ui.label("Chart");
+            egui::ComboBox::from_id_source("chart kind")
+                .selected_text(self.spec.kind.name())
+                .show_ui(ui, |ui| {
+                    for kind in ChartKind::ALL {
+                        ui.selectable_value(&mut self.spec.kind, kind, kind.name());
+                    }
+                });
+            let mut x = Some(self.spec.x.clone());
+            column_picker(ui, "X", &mut x, &fields, None);
+            if let Some(x) = x {
+                self.spec.x = x;
+            }
+            if self.spec.kind.has_y() {
+                let none = (self.spec.kind == ChartKind::Bar).then_some("Count");
+                column_picker(ui, "Y", &mut self.spec.y, &numeric, none);
+            }
+            column_picker(ui, "Group", &mut self.spec.group, &fields, Some("None"));
+            if self.pending.is_some() {
+                ui.ctx().request_repaint();
+                ui.spinner();
+            }
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.small_button("✖").clicked() {
+                    self.open = false;
+                }
+            });
+        });
+        if self.spec != spec {
+            self.run();
+        }
+
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.style().visuals.error_fg_color, error);
+        } else if let Some(chart) = &self.chart {
+            if chart.downsampled {
+                ui.weak("Too many rows to plot, each series is averaged over ranges of X");
+            }
+            chart.show(ui);
+        }
+        None
+    }
+}
+
+impl ShowMut for ProfilePanel {
+    fn show(&mut self, ui: &mut Ui) -> Option<Action> {
+        let mut action = None;
+        if self.pending.as_ref().is_some_and(|task| task.is_finished()) {
+            let task = self.pending.take().expect("Checked above");
+            match smol::block_on(task) {
+                Ok(profile) => self.profile = Some(profile),
+                Err(err) => {
+                    action = Some(Action::LogError(err));
+                    self.open = false;
+                }
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.heading(&self.column);
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.small_button("✖").clicked() {
+                    self.open = false;
+                }
+            });
+        });
+        if ui
+            .checkbox(&mut self.exact, "Exact distinct count")
+            .changed()
+        {
+            self.run();
+        }
+        if self.pending.is_some() {
+            ui.ctx().request_repaint();
+            ui.spinner();
+        }
+        if let Some(profile) = &self.profile {
+            profile.show(ui);
+        }
+        action
+    }
+}
+
+impl Show for ColumnProfile {
+    fn show(&self, ui: &mut Ui) -> Option<Action> {
+        let percent = |count: i64| {
+            if self.rows > 0 {
+                format!("{:.1}%", 100.0 * count as f64 / self.rows as f64)
+            } else {
+                "".to_owned()
+            }
+        };
+
+        egui::Grid::new("profile_stats")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                let mut stat = |name: &str, value: String| {
+                    ui.label(name);
+                    ui.label(value);
+                    ui.end_row();
+                };
+                stat("Type", self.data_type.to_string());
+                stat("Rows", self.rows.to_string());
+                stat("Nulls", format!("{} ({})", self.nulls, percent(self.nulls)));
+                stat(
+                    if self.distinct_exact {
+                        "Distinct"
+                    } else {
+                        "Distinct (approx.)"
+                    },
+                    self.distinct.to_string(),
+                );
+                if let Some(min) = &self.min {
+                    stat("Min", min.clone());
+                }
+                if let Some(max) = &self.max {
+                    stat("Max", max.clone());
+                }
+                if let Some(mean) = self.mean {
+                    stat("Mean", format_bin(mean, &self.data_type));
+                }
+                if let Some(stddev) = self.stddev {
+                    stat("Std. Dev.", format!("{}", stddev));
+                }
+            });
+
+        if !self.top_values.is_empty() {
+            ui.separator();
+            ui.label("Most frequent");
+            egui::Grid::new("profile_top_values")
+                .num_columns(3)
+                .striped(true)
+                .show(ui, |ui| {
+                    for (value, count) in &self.top_values {
+                        ui.label(value);
+                        ui.label(count.to_string());
+                        ui.label(percent(*count));
+                        ui.end_row();
+                    }
+                });
+        }
+
+        if !self.histogram.is_empty() {
+            ui.separator();
+            ui.label("Histogram");
+            let bars = self
+                .histogram
+                .iter()
+                .map(|(start, count)| {
+                    Bar::new(start + self.bin_width / 2.0, *count as f64).width(self.bin_width)
+                })
+                .collect();
+            let data_type = self.data_type.clone();
+            Plot::new("profile_histogram")
+                .height(ui.style().spacing.text_edit_width)
+                .allow_drag(false)
+                .allow_zoom(false)
+                .allow_scroll(false)
+                .allow_boxed_zoom(false)
+                .x_axis_formatter(move |mark, _, _| format_bin(mark.value, &data_type))
+                .show(ui, |plot| plot.bar_chart(BarChart::new(bars)));
+        }
+        None
+    }
+}
+
+impl Popover for CellInspector {
+    fn popover(&mut self, ctx: &Context) -> (bool, Option<Action>) {
+        let mut open = true;
+        let mut action = None;
+
+        egui::Window::new(format!("{}, row {}", self.column, self.row + 1))
+            .id(self.id)
+            .collapsible(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label(egui::RichText::new(self.data_type.to_string()).weak());
+                let rewrite = match self.data_type {
+                    DataType::Struct(_) => Some((
+                        "Expand into columns",
+                        Action::ExpandStruct(self.column.clone()),
+                    )),
+                    DataType::List(_) | DataType::LargeList(_) | DataType::FixedSizeList(_, _) => {
+                        Some((
+                            "Explode into rows",
+                            Action::ExplodeList(self.column.clone()),
+                        ))
+                    }
+                    _ => None,
+                };
+                if let Some((label, rewrite)) = rewrite {
+                    if ui.button(label).clicked() {
+                        action = Some(rewrite);
+                    }
+                }
+                ui.separator();
+                egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                    JsonTree::new(self.id.with("tree"), &self.value)
+                        .default_expand(DefaultExpand::ToLevel(0))
+                        .show(ui);
+                });
+            });
+
+        // the inspected value may no longer exist once the query is rewritten
+        (open && action.is_none(), action)
+    }
+}
+
+/// Converts a 1 based line and column, as reported by the sql tokenizer, into a byte offset.
+fn location_to_offset(text: &str, line: u64, column: u64) -> usize {
+    let line_start: usize = text
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1) as usize)
+        .map(str::len)
+        .sum();
+    text[line_start..]
+        .char_indices()
+        .nth(column.saturating_sub(1) as usize)
+        .map_or(text.len(), |(offset, _)| line_start + offset)
+}
+
+fn parse_sql_error(sql: &str) -> Option<SqlError> {
+    if sql.trim().is_empty() {
+        return None;
+    }
+    let err = DFParser::parse_sql(sql).err()?;
+    // sqlparser only reports the location as part of the message
+    let location_regex =
+        Regex::new(r"Line: (\d+), Column:? (\d+)").expect("Invalid location regex");
+    let locate = |message: &str| {
+        location_regex.captures(message).map(|captures| {
+            location_to_offset(
+                sql,
+                captures[1].parse().unwrap_or_default(),
+                captures[2].parse().unwrap_or_default(),
+            )
+        })
+    };
+
+    let message = err.to_string();
+    if let Some(offset) = locate(&message) {
+        return Some(SqlError {
+            message,
+            offset: Some(offset),
+        });
+    }
+    // errors raised by DataFusion's own statements have no location, but the plain sql parser
+    // usually fails at the same token
+    match Parser::parse_sql(&GenericDialect {}, sql) {
+        Err(err) if locate(&err.to_string()).is_some() => {
+            let message = err.to_string();
+            Some(SqlError {
+                offset: locate(&message),
+                message,
+            })
+        }
+        _ => Some(SqlError {
+            message,
+            offset: None,
+        }),
+    }
+}
+
+fn highlight_sql(ui: &Ui, text: &str, error_offset: Option<usize>) -> LayoutJob {
+    let visuals = &ui.style().visuals;
+    let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+    let (keyword, string, number, comment) = if visuals.dark_mode {
+        (
+            Color32::from_rgb(255, 100, 100),
+            Color32::from_rgb(109, 147, 226),
+            Color32::from_rgb(87, 165, 171),
+            Color32::from_gray(120),
+        )
+    } else {
+        (
+            Color32::from_rgb(235, 0, 0),
+            Color32::from_rgb(37, 203, 105),
+            Color32::from_rgb(153, 134, 255),
+            Color32::GRAY,
+        )
+    };
+
+    let dialect = GenericDialect {};
+    let mut tokens = vec![];
+    // on failure, everything from the failing token onwards is left unhighlighted
+    let end = match Tokenizer::new(&dialect, text).tokenize_with_location_into_buf(&mut tokens) {
+        Ok(_) => text.len(),
+        Err(err) => location_to_offset(text, err.location.line, err.location.column),
+    };
+    let starts = tokens
+        .iter()
+        .map(|token| location_to_offset(text, token.location.line, token.location.column))
+        .chain([end])
+        .collect_vec();
+
+    let mut job = LayoutJob::default();
+    let mut append = |range: Range<usize>, color: Color32| {
+        let mut format = egui::TextFormat::simple(font_id.clone(), color);
+        if error_offset.is_some_and(|offset| range.contains(&offset)) {
+            format.underline = egui::Stroke::new(1.0, visuals.error_fg_color);
+        }
+        job.append(&text[range], 0.0, format);
+    };
+    for (token, (&start, &next)) in tokens.iter().zip(starts.iter().tuple_windows()) {
+        let color = match &token.token {
+            Token::Word(word) if word.keyword != Keyword::NoKeyword => keyword,
+            Token::Number(_, _) => number,
+            Token::SingleQuotedString(_)
+            | Token::EscapedStringLiteral(_)
+            | Token::NationalStringLiteral(_)
+            | Token::HexStringLiteral(_)
+            | Token::DollarQuotedString(_) => string,
+            Token::Whitespace(
+                Whitespace::SingleLineComment { .. } | Whitespace::MultiLineComment(_),
+            ) => comment,
+            _ => visuals.text_color(),
+        };
+        append(start..next, color);
+    }
+    if end < text.len() {
+        append(end..text.len(), visuals.text_color());
+    }
+    job
+}
+
+impl QueryBuilder {
+    pub fn new(functions: Vec<String>) -> Self {
+        Self {
+            functions,
+            ..Default::default()
+        }
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Replaces the query being edited.
+    pub fn set_query(&mut self, query: String) {
+        self.query = query;
+        self.completions.clear();
+    }
+
+    /// Updates the tables and columns offered as completions.
+    pub fn set_tables(&mut self, tables: &DataSourceListing) {
+        self.tables = tables
+            .iter()
+            .map(|(name, table)| (name.clone(), table.schema()))
+            .collect();
+    }
+
+    fn update_completions(&mut self, cursor: usize) {
+        let before = &self.query[..cursor];
+        let start = before
+            .rfind(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .map_or(0, |i| {
+                i + before[i..].chars().next().map_or(1, char::len_utf8)
+            });
+        let prefix = before[start..].to_lowercase();
+        // a qualified name, `table.col`, only completes columns of that table
+        let qualifier = before[..start].strip_suffix('.').map(|qualified| {
+            let qualifier_start = qualified
+                .rfind(|c: char| !(c.is_alphanumeric() || c == '_' || c == '"'))
+                .map_or(0, |i| i + 1);
+            qualified[qualifier_start..]
+                .trim_matches('"')
+                .to_lowercase()
+        });
+
+        self.completion_range = start..cursor;
+        self.completions =
+            if self.completions_dismissed || (prefix.is_empty() && qualifier.is_none()) {
+                vec![]
+            } else {
+                let columns = |schema: &SchemaRef| {
+                    schema
+                        .fields()
+                        .iter()
+                        .map(|field| Completion {
+                            text: quote_identifier(field.name()),
+                            kind: "column",
+                        })
+                        .collect_vec()
+                };
+                let candidates = match qualifier.and_then(|table| self.tables.get(&table)) {
+                    Some(schema) => columns(schema),
+                    None => self
+                        .tables
+                        .keys()
+                        .map(|table| Completion {
+                            text: quote_identifier(table),
+                            kind: "table",
+                        })
+                        .chain(self.tables.values().flat_map(columns))
+                        .chain(self.functions.iter().map(|function| Completion {
+                            text: function.clone(),
+                            kind: "function",
+                        }))
+                        .collect(),
+                };
+                candidates
+                    .into_iter()
+                    .filter(|completion| {
+                        let text = completion.text.trim_start_matches('"').to_lowercase();
+                        text.starts_with(&prefix) && text != prefix
+                    })
+                    .unique_by(|completion| completion.text.clone())
+                    .take(MAX_COMPLETIONS)
+                    .collect()
+            };
+        self.selected_completion = self
+            .selected_completion
+            .min(self.completions.len().saturating_sub(1));
+    }
+
+    fn accept_completion(&mut self, ctx: &Context, id: Id) {
+        let Some(completion) = self.completions.get(self.selected_completion) else {
+            return;
+        };
+        let range = self.completion_range.clone();
+        self.query.replace_range(range.clone(), &completion.text);
+        let cursor = self.query[..range.start].chars().count() + completion.text.chars().count();
+        if let Some(mut state) = egui::TextEdit::load_state(ctx, id) {
+            state
+                .cursor
+                .set_char_range(Some(CCursorRange::one(CCursor::new(cursor))));
+            state.store(ctx, id);
+        }
+        ctx.memory_mut(|memory| memory.request_focus(id));
+        self.completions.clear();
+    }
+}
+
+impl ShowMut for QueryBuilder {
+    fn show(&mut self, ui: &mut Ui) -> Option<Action> {
+        let id = Id::new("query_builder_editor");
+        let mut action = None;
+
+        // keys are taken before the editor sees them, so they don't also move the cursor
+        if ui.memory(|memory| memory.has_focus(id)) {
+            ui.input_mut(|input| {
+                if input.consume_key(Modifiers::COMMAND, Key::Enter) {
+                    action = Some(Action::QuerySource(Query::Sql(self.query.to_owned())));
+                }
+                if self.completions.is_empty() {
+                    return;
+                }
+                if input.consume_key(Modifiers::NONE, Key::ArrowDown) {
+                    self.selected_completion =
+                        (self.selected_completion + 1) % self.completions.len();
+                }
+                if input.consume_key(Modifiers::NONE, Key::ArrowUp) {
+                    self.selected_completion = self
+                        .selected_completion
+                        .checked_sub(1)
+                        .unwrap_or(self.completions.len() - 1);
+                }
+                if input.consume_key(Modifiers::NONE, Key::Escape) {
+                    self.completions_dismissed = true;
+                    self.completions.clear();
+                }
+            });
+            if ui.input_mut(|input| input.consume_key(Modifiers::NONE, Key::Tab)) {
+                self.accept_completion(ui.ctx(), id);
+            }
+        }
+
+        if self.parsed_query != self.query {
+            self.parse_error = parse_sql_error(&self.query);
+            self.parsed_query.clone_from(&self.query);
+        }
+        let error_offset = self.parse_error.as_ref().and_then(|err| err.offset);
+        let mut layouter = |ui: &Ui, text: &str, wrap_width: f32| {
+            let mut job = highlight_sql(ui, text, error_offset);
+            job.wrap.max_width = wrap_width;
+            ui.fonts(|fonts| fonts.layout_job(job))
+        };
+        let output = egui::TextEdit::multiline(&mut self.query)
+            .id(id)
+            .code_editor()
+            .desired_width(f32::INFINITY)
+            .hint_text("SELECT * FROM ...")
+            .layouter(&mut layouter)
+            .show(ui);
+
+        if output.response.changed() {
+            self.completions_dismissed = false;
+        }
+        match output.cursor_range {
+            Some(cursor_range) if output.response.has_focus() => {
+                let cursor = cursor_range.primary;
+                let offset = self
+                    .query
+                    .char_indices()
+                    .nth(cursor.ccursor.index)
+                    .map_or(self.query.len(), |(offset, _)| offset);
+                self.update_completions(offset);
+
+                if !self.completions.is_empty() {
+                    let pos = output.galley_pos
+                        + output
+                            .galley
+                            .pos_from_cursor(&cursor)
+                            .left_bottom()
+                            .to_vec2();
+                    let mut clicked = None;
+                    egui::Area::new(id.with("completions"))
+                        .order(egui::Order::Foreground)
+                        .fixed_pos(pos)
+                        .show(ui.ctx(), |ui| {
+                            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                                for (i, completion) in self.completions.iter().enumerate() {
+                                    ui.horizontal(|ui| {
+                                        if ui
+                                            .selectable_label(
+                                                i == self.selected_completion,
+                                                &completion.text,
+                                            )
+                                            .clicked()
+                                        {
+                                            clicked = Some(i);
+                                        }
+                                        ui.weak(completion.kind);
+                                    });
+                                }
+                            });
+                        });
+                    if let Some(i) = clicked {
+                        self.selected_completion = i;
+                        self.accept_completion(ui.ctx(), id);
+                    }
+                }
+            }
+            _ => self.completions.clear(),
+        }
+
+        if let Some(err) = &self.parse_error {
+            ui.add(egui::Label::new(
+                egui::RichText::new(&err.message).color(ui.style().visuals.error_fg_color),
+            ));
+        }
+
+        ui.horizontal(|ui| {
+            let submit = ui.button("Query").on_hover_text("Ctrl+Enter");
+            if submit.clicked() {
+                action = Some(Action::QuerySource(Query::Sql(self.query.to_owned())));
+            }
+            if ui
+                .add_enabled(!self.query.trim().is_empty(), egui::Button::new("Explain"))
+                .on_hover_text("Show how the query will run")
+                .clicked()
+            {
+                action = Some(Action::ExplainQuery((self.query.to_owned(), false)));
+            }
+            if ui
+                .add_enabled(!self.query.trim().is_empty(), egui::Button::new("Save"))
+                .clicked()
+            {
+                action = Some(Action::ShowPopover(Box::new(SaveQuery::new(
+                    self.query.trim().to_owned(),
+                ))));
+            }
+        });
+        action
+    }
+}
+
+/// Timing and size of a past query, on one line.
+fn history_summary(entry: &HistoryEntry) -> String {
+    let mut summary = vec![DateTime::<Local>::from(entry.started)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()];
+    if let Some(duration) = entry.duration {
+        summary.push(format!("{:.2?}", duration));
+    }
+    if let Some(rows) = entry.rows {
+        summary.push(format!("{} rows", rows));
+    }
+    if !entry.tables.is_empty() {
+        summary.push(entry.tables.join(", "));
+    }
+    summary.join(" · ")
+}
+
+/// Right click menu of a saved query. Returns true if it should be deleted.
+fn saved_query_menu(
+    ui: &mut Ui,
+    query: &mut SavedQuery,
+    folders: &[String],
+    action: &mut Option<Action>,
+) -> bool {
+    if ui.button("Edit").clicked() {
+        *action = Some(Action::EditQuery(query.sql.clone()));
+        ui.close_menu();
+    }
+    ui.horizontal(|ui| {
+        ui.label("Rename");
+        ui.text_edit_singleline(&mut query.name);
+    });
+    ui.menu_button("Move to", |ui| {
+        for folder in folders {
+            if ui
+                .add_enabled(*folder != query.folder, egui::Button::new(folder))
+                .clicked()
+            {
+                query.folder.clone_from(folder);
+                ui.close_menu();
+            }
+        }
+        if !query.folder.is_empty() && ui.button("No folder").clicked() {
+            query.folder.clear();
+            ui.close_menu();
+        }
+        ui.separator();
+        // typed into a draft, so the query isn't moved on every key
+        let draft_id = ui.id().with("new_folder");
+        let mut draft = ui.data_mut(|data| data.get_temp::<String>(draft_id).unwrap_or_default());
+        let response = ui.add(egui::TextEdit::singleline(&mut draft).hint_text("New folder"));
+        if response.lost_focus() && ui.input(|input| input.key_pressed(Key::Enter)) {
+            query.folder = draft.trim().to_owned();
+            draft.clear();
+            ui.close_menu();
+        }
+        ui.data_mut(|data| data.insert_temp(draft_id, draft));
+    });
+    let delete = ui.button("Delete").clicked();
+    if delete {
+        ui.close_menu();
+    }
+    delete
+}
+
+impl ShowMut for QueryHistory {
+    fn show(&mut self, ui: &mut Ui) -> Option<Action> {
+        let mut action = None;
+
+        ui.strong("Saved");
+        if self.saved.is_empty() {
+            ui.weak("Save a query to run it again later");
+        }
+        let folders = self.folders().into_iter().map(str::to_owned).collect_vec();
+        let mut delete = None;
+        let mut show_saved = |ui: &mut Ui, index: usize, query: &mut SavedQuery| {
+            ui.push_id(index, |ui| {
+                let response = ui
+                    .add(egui::Label::new(&query.name).sense(egui::Sense::click()))
+                    .on_hover_text(egui::RichText::new(&query.sql).monospace());
+                if response.clicked() {
+                    action = Some(Action::QuerySource(Query::Sql(query.sql.clone())));
+                }
+                response.context_menu(|ui| {
+                    if saved_query_menu(ui, query, &folders, &mut action) {
+                        delete = Some(index);
+                    }
+                });
+            });
+        };
+        for folder in &folders {
+            egui::CollapsingHeader::new(format!("🗀 {}", folder))
+                .id_source(("saved_query_folder", folder))
+                .show(ui, |ui| {
+                    for (index, query) in self.saved.iter_mut().enumerate() {
+                        if query.folder == *folder {
+                            show_saved(ui, index, query);
+                        }
+                    }
+                });
+        }
+        for (index, query) in self.saved.iter_mut().enumerate() {
+            if query.folder.is_empty() {
+                show_saved(ui, index, query);
+            }
+        }
+        if let Some(index) = delete {
+            self.saved.remove(index);
+        }
+
+        ui.separator();
+        ui.strong("History");
+        ui.add(egui::TextEdit::singleline(&mut self.search).hint_text("Search history"));
+        let mut remove = None;
+        for entry in self
+            .entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.matches(&self.search))
+        {
+            ui.push_id(entry.id, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.small_button("▶").on_hover_text("Run").clicked() {
+                        action = Some(Action::QuerySource(Query::Sql(entry.sql.clone())));
+                    }
+                    let first_line = entry.sql.lines().next().unwrap_or_default();
+                    let response = ui
+                        .add(
+                            egui::Label::new(egui::RichText::new(first_line).monospace())
+                                .truncate(true)
+                                .sense(egui::Sense::click()),
+                        )
+                        .on_hover_text(egui::RichText::new(&entry.sql).monospace());
+                    if response.clicked() {
+                        action = Some(Action::EditQuery(entry.sql.clone()));
+                    }
+                    response.context_menu(|ui| {
+                        if ui.button("Edit").clicked() {
+                            action = Some(Action::EditQuery(entry.sql.clone()));
+                            ui.close_menu();
+                        }
+                        if ui.button("Save...").clicked() {
+                            action = Some(Action::ShowPopover(Box::new(SaveQuery::new(
+                                entry.sql.clone(),
+                            ))));
+                            ui.close_menu();
+                        }
+                        if ui.button("Remove from history").clicked() {
+                            remove = Some(entry.id);
+                            ui.close_menu();
+                        }
+                    });
+                });
+                match &entry.error {
+                    Some(err) => {
+                        ui.add(egui::Label::new(
+                            egui::RichText::new(history_summary(entry))
+                                .small()
+                                .color(ui.style().visuals.error_fg_color),
+                        ))
+                        .on_hover_text(err);
+                    }
+                    None => {
+                        ui.add(egui::Label::new(
+                            egui::RichText::new(history_summary(entry)).small().weak(),
+                        ));
+                    }
+                }
+            });
+        }
+        if let Some(id) = remove {
+            self.entries.retain(|entry| entry.id != id);
+        }
+        if !self.entries.is_empty() && ui.button("Clear history").clicked() {
+            self.entries.clear();
+        }
+        action
+    }
+}
+
+/// Sort after clicking on a column. Clicking sorts by only that column, unless `extend` is set,
+/// which adds the column as the lowest priority key or changes its direction if it's present.
+fn with_sort_key(
+    sort: &[SortKey],
+    column: &str,
+    direction: SortState,
+    extend: bool,
+) -> Vec<SortKey> {
+    let existing = sort.iter().find(|key| key.column == column);
+    let key = match existing {
+        Some(key) => SortKey {
+            direction,
+            ..key.clone()
+        },
+        None => SortKey::new(column, direction),
+    };
+    if !extend {
+        return vec![key];
+    }
+    let mut sort = sort.to_vec();
+    match sort.iter_mut().find(|key| key.column == column) {
+        Some(existing) => *existing = key,
+        None => sort.push(key),
+    }
+    sort
+}
+
+/// Right click menu of a column's sort button. Returns the new sort if it was changed.
+fn sort_menu(ui: &mut Ui, sort: &[SortKey], column: &str) -> Option<Vec<SortKey>> {
+    let mut new_sort = None;
+    match sort.iter().position(|key| key.column == column) {
+        Some(priority) => {
+            let mut nulls_first = sort[priority].nulls_first;
+            let first = ui.radio_value(&mut nulls_first, true, "Nulls first");
+            let last = ui.radio_value(&mut nulls_first, false, "Nulls last");
+            if first.clicked() || last.clicked() {
+                let mut sort = sort.to_vec();
+                sort[priority].nulls_first = nulls_first;
+                new_sort = Some(sort);
+            }
+            ui.separator();
+            if ui.button("Remove from sort").clicked() {
+                let mut sort = sort.to_vec();
+                sort.remove(priority);
+                new_sort = Some(sort);
+            }
+        }
+        None => {
+            if ui.button("Add to sort").clicked() {
+                new_sort = Some(with_sort_key(sort, column, SortState::Ascending, true));
+            }
+        }
+    }
+    if !sort.is_empty() && ui.button("Clear sort").clicked() {
+        new_sort = Some(vec![]);
+    }
+    new_sort
+}
+
+/// Values being entered in a column's filter menu, kept in egui memory between frames.
+#[derive(Clone, Default)]
+struct FilterDraft {
+    text: String,
+    regex: bool,
+    min: String,
+    max: String,
+    picked: BTreeSet<String>,
+    error: Option<String>,
+}
+
+fn is_text(t: &DataType) -> bool {
+    match t {
+        DataType::Utf8 | DataType::LargeUtf8 => true,
+        DataType::Dictionary(_, value) => is_text(value),
+        _ => false,
+    }
+}
+
+/// Adds `filter` to `filters`, replacing a filter of the same kind on the same column.
+fn with_filter(filters: &[Filter], filter: Filter) -> Vec<Filter> {
+    let mut filters = filters
+        .iter()
+        .filter(|existing| {
+            existing.column != filter.column
+                || std::mem::discriminant(&existing.filter)
+                    != std::mem::discriminant(&filter.filter)
+        })
+        .cloned()
+        .collect_vec();
+    filters.push(filter);
+    filters
+}
+
+/// Contents of the filter menu in a column header. Returns the new filters if one was applied.
+fn filter_menu(ui: &mut Ui, data: &mut Data, field: &Field, draft_id: Id) -> Option<Vec<Filter>> {
+    let mut draft = ui.data_mut(|data| data.get_temp::<FilterDraft>(draft_id).unwrap_or_default());
+    let data_type = field.data_type();
+    let mut filter = None;
+
+    ui.label(egui::RichText::new(format!("{}: {}", field.name(), data_type)).weak());
+    if is_text(data_type) {
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut draft.text).hint_text(if draft.regex {
+                    "regex"
+                } else {
+                    "contains"
+                }),
+            );
+            ui.checkbox(&mut draft.regex, ".*")
+                .on_hover_text("Match a regular expression");
+            if ui.button("Apply").clicked() && !draft.text.is_empty() {
+                filter = Some(if draft.regex {
+                    ColumnFilter::Regex(draft.text.clone())
+                } else {
+                    ColumnFilter::Contains(draft.text.clone())
+                });
+            }
+        });
+    }
+    if data_type.is_numeric() || data_type.is_temporal() {
+        ui.horizontal(|ui| {
+            let width = ui.style().spacing.text_edit_width / 2.0;
+            ui.add(
+                egui::TextEdit::singleline(&mut draft.min)
+                    .hint_text("min")
+                    .desired_width(width),
+            );
+            ui.label("to");
+            ui.add(
+                egui::TextEdit::singleline(&mut draft.max)
+                    .hint_text("max")
+                    .desired_width(width),
+            );
+            let bound = |value: &str| (!value.trim().is_empty()).then(|| value.trim().to_owned());
+            let (min, max) = (bound(&draft.min), bound(&draft.max));
+            if ui.button("Apply").clicked() && (min.is_some() || max.is_some()) {
+                filter = Some(ColumnFilter::Range { min, max });
+            }
+        });
+    }
+    // floats rarely repeat, nested values can't be compared to a literal, and binary values are
+    // shown as hex, which would be cast back to the bytes of the text rather than parsed
+    if !(is_float(data_type) || data_type.is_nested() || is_binary(data_type)) {
+        match data.distinct_values(field.name()) {
+            None => {
+                ui.spinner();
+            }
+            Some(None) => {
+                ui.weak("Too many values to list");
+            }
+            Some(Some(values)) => {
+                egui::ScrollArea::vertical()
+                    .max_height(ui.style().spacing.text_edit_width)
+                    .show(ui, |ui| {
+                        for value in values {
+                            let mut picked = draft.picked.contains(value);
+                            if ui.checkbox(&mut picked, value).changed() {
+                                if picked {
+                                    draft.picked.insert(value.clone());
+                                } else {
+                                    draft.picked.remove(value);
+                                }
+                            }
+                        }
+                    });
+                if ui.button("Apply").clicked() && !draft.picked.is_empty() {
+                    filter = Some(ColumnFilter::OneOf(draft.picked.iter().cloned().collect()));
+                }
+            }
+        }
+    }
+    ui.separator();
+    ui.horizontal(|ui| {
+        if ui.button("Is null").clicked() {
+            filter = Some(ColumnFilter::IsNull);
+        }
+        if ui.button("Not null").clicked() {
+            filter = Some(ColumnFilter::IsNotNull);
+        }
+    });
+    if let Some(err) = &draft.error {
+        ui.colored_label(ui.style().visuals.error_fg_color, err);
+    }
+
+    // check the filter here, so a bad value doesn't replace the data with an error
+    let filters = filter.and_then(|filter| {
+        let filter = Filter::new(field.name(), data_type, filter);
+        match filter.to_expr() {
+            Ok(_) => {
+                draft.error = None;
+                Some(with_filter(&data.filters, filter))
+            }
+            Err(err) => {
+                draft.error = Some(err.to_string());
+                None
+            }
+        }
+    });
+    if filters.is_some() {
+        ui.close_menu();
+    }
+    ui.data_mut(|data| data.insert_temp(draft_id, draft));
+    filters
+}
+
+/// Summary of a comparison above its rows. Clicking a count shows only the rows it counts,
+/// returning the filters to apply.
+fn show_comparison(
+    ui: &mut Ui,
+    comparison: &Comparison,
+    filters: &[Filter],
+) -> Option<Vec<Filter>> {
+    let mut picked = None;
+    ui.horizontal_wrapped(|ui| {
+        ui.strong(format!("{} ⇄ {}", comparison.left, comparison.right));
+        ui.weak(format!("on {}", comparison.keys.join(", ")));
+        ui.separator();
+        for (status, rows) in [
+            (compare::LEFT_ONLY, comparison.left_only_rows),
+            (compare::RIGHT_ONLY, comparison.right_only_rows),
+            (compare::CHANGED, comparison.changed_rows),
+        ] {
+            if ui
+                .link(format!("{} {}", rows, status))
+                .on_hover_text("Show only these rows")
+                .clicked()
+            {
+                picked = Some(status);
+            }
+        }
+    });
+    if comparison.schemas_differ() {
+        ui.collapsing("Schema differences", |ui| {
+            for column in &comparison.left_only_columns {
+                ui.label(format!("{} is only in {}", column, comparison.left));
+            }
+            for column in &comparison.right_only_columns {
+                ui.label(format!("{} is only in {}", column, comparison.right));
+            }
+            for (column, left_type, right_type) in &comparison.changed_types {
+                ui.label(format!(
+                    "{} is {} in {} but {} in {}",
+                    column, left_type, comparison.left, right_type, comparison.right
+                ));
+            }
+        });
+    }
+    picked.map(|status| {
+        let mut filters = filters
+            .iter()
+            .filter(|filter| filter.column != compare::STATUS_COLUMN)
+            .cloned()
+            .collect_vec();
+        filters.push(Filter::new(
+            compare::STATUS_COLUMN,
+            &DataType::Utf8,
+            ColumnFilter::OneOf(vec![status.to_owned()]),
+        ));
+        filters
+    })
+}
+
+fn scroll_offset(ctx: &Context, scroll_id: Id) -> Option<f32> {
+    egui::scroll_area::State::load(ctx, scroll_id).map(|state| state.offset.y)
+}
+
+/// Menu of the columns of a result, to search, show, hide, pin and reorder them. Returns the
+/// changed layout.
+fn column_chooser(ui: &mut Ui, layout: &ColumnLayout, schema: &Schema) -> Option<ColumnLayout> {
+    let ordered = layout.ordered(schema);
+    let hidden = ordered
+        .iter()
+        .filter(|&&index| layout.is_hidden(schema.field(index).name()))
+        .count();
+    let label = if hidden > 0 {
+        format!("Columns ({} of {})", ordered.len() - hidden, ordered.len())
+    } else {
+        "Columns".to_owned()
+    };
+    let mut changed = layout.clone();
+    ui.menu_button(label, |ui| {
+        let search_id = ui.id().with("column_search");
+        let mut search = ui
+            .data(|data| data.get_temp::<String>(search_id))
+            .unwrap_or_default();
+        ui.add(egui::TextEdit::singleline(&mut search).hint_text("Search columns"));
+        let needle = search.to_lowercase();
+        // positions in the order are kept, to move columns while searching
+        let matching = ordered
+            .iter()
+            .enumerate()
+            .map(|(position, &index)| (position, schema.field(index).name()))
+            .filter(|(_, name)| name.to_lowercase().contains(&needle))
+            .collect_vec();
+        ui.horizontal(|ui| {
+            if ui.button("Show all").clicked() {
+                for (_, name) in &matching {
+                    changed.set_hidden(name, false);
+                }
+            }
+            if ui.button("Hide all").clicked() {
+                for (_, name) in &matching {
+                    changed.set_hidden(name, true);
+                }
+            }
+            if ui
+                .button("Reset")
+                .on_hover_text("Show every column unpinned, in the order of the schema")
+                .clicked()
+            {
+                changed = ColumnLayout::default();
+            }
+        });
+        ui.separator();
+        egui::ScrollArea::vertical()
+            .max_height(ui.ctx().screen_rect().height() / 2.0)
+            .show(ui, |ui| {
+                for &(position, name) in &matching {
+                    let row = ui
+                        .horizontal(|ui| {
+                            ui.dnd_drag_source(
+                                Id::new(("column_chooser", position)),
+                                position,
+                                |ui| {
+                                    ui.label("☰");
+                                },
+                            )
+                            .response
+                            .on_hover_text("Drag to reorder");
+                            let mut shown = !changed.is_hidden(name);
+                            if ui.checkbox(&mut shown, name.as_str()).changed() {
+                                changed.set_hidden(name, !shown);
+                            }
+                            let mut pinned = changed.is_pinned(name);
+                            if ui
+                                .toggle_value(&mut pinned, "📌")
+                                .on_hover_text("Pin to the left, to keep in view while scrolling")
+                                .changed()
+                            {
+                                changed.set_pinned(name, pinned);
+                            }
+                        })
+                        .response;
+                    if let Some(from) = row.dnd_release_payload::<usize>() {
+                        changed.move_column(schema, *from, position);
+                    } else if row.dnd_hover_payload::<usize>().is_some() {
+                        ui.painter().hline(
+                            row.rect.x_range(),
+                            row.rect.top(),
+                            ui.visuals().selection.stroke,
+                        );
+                    }
+                }
+            });
+        ui.data_mut(|data| data.insert_temp(search_id, search));
+    });
+    (changed != *layout).then_some(changed)
+}
+
+/// Settings of `format` that apply to `kind`, or all of them for a format of a particular column.
+fn value_format_editor(ui: &mut Ui, id: Id, format: &mut ValueFormat, kind: Option<ValueKind>) {
+    let applies = |applies_to: ValueKind| kind.map_or(true, |kind| kind == applies_to);
+    egui::Grid::new(id).num_columns(2).show(ui, |ui| {
+        if applies(ValueKind::Integer) || applies(ValueKind::Float) {
+            ui.label("Decimal places");
+            ui.horizontal(|ui| {
+                let mut fixed = format.decimals.is_some();
+                if ui.checkbox(&mut fixed, "").changed() {
+                    format.decimals = fixed.then_some(2);
+                }
+                match &mut format.decimals {
+                    Some(decimals) => {
+                        ui.add(egui::DragValue::new(decimals).clamp_range(0..=20));
+                    }
+                    None => {
+                        ui.weak("all");
+                    }
+                }
+            });
+            ui.end_row();
+            ui.label("Thousands separator");
+            ui.checkbox(&mut format.thousands_separator, "");
+            ui.end_row();
+            ui.label("Scientific notation");
+            ui.checkbox(&mut format.scientific, "");
+            ui.end_row();
+        }
+        if applies(ValueKind::Integer) {
+            ui.label("Integers as");
+            egui::ComboBox::from_id_source(id.with("epoch"))
+                .selected_text(format.epoch.map_or("Numbers", |unit| unit.name()))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut format.epoch, None, "Numbers");
+                    for unit in EpochUnit::ALL {
+                        ui.selectable_value(&mut format.epoch, Some(unit), unit.name());
+                    }
+                });
+            ui.end_row();
+        }
+        // integers shown as timestamps take the timestamp settings too
+        if applies(ValueKind::Timestamp) || format.epoch.is_some() {
+            ui.label("Timezone");
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut format.timezone).hint_text("As stored"))
+                    .on_hover_text("A name such as Europe/Paris, or an offset such as +02:00");
+                if !format.timezone.is_empty() && !is_timezone(&format.timezone) {
+                    ui.colored_label(ui.style().visuals.error_fg_color, "Unknown timezone");
+                }
+            });
+            ui.end_row();
+            ui.label("Timestamp format");
+            ui.add(
+                egui::TextEdit::singleline(&mut format.timestamp_format)
+                    .hint_text("%Y-%m-%dT%H:%M:%S"),
+            )
+            .on_hover_text("A chrono format string, such as %Y-%m-%d %H:%M");
+            ui.end_row();
+        }
+        if applies(ValueKind::Binary) {
+            ui.label("Binary as");
+            egui::ComboBox::from_id_source(id.with("binary"))
+                .selected_text(format.binary.name())
+                .show_ui(ui, |ui| {
+                    for binary in BinaryFormat::ALL {
+                        ui.selectable_value(&mut format.binary, binary, binary.name());
+                    }
+                });
+            ui.end_row();
+        }
+    });
+}
+
+impl ShowMut for DisplayFormats {
+    fn show(&mut self, ui: &mut Ui) -> Option<Action> {
+        ui.horizontal(|ui| {
+            ui.label("Nulls shown as");
+            ui.text_edit_singleline(&mut self.null);
+        });
+        for kind in ValueKind::ALL {
+            egui::CollapsingHeader::new(kind.name())
+                .id_source(("display_format", kind.name()))
+                .show(ui, |ui| {
+                    let id = ui.id().with("format");
+                    value_format_editor(ui, id, self.kind_mut(kind), Some(kind));
+                });
+        }
+        ui.label("Columns, formatted instead of by their type");
+        let mut removed = None;
+        for (column, format) in &mut self.columns {
+            egui::CollapsingHeader::new(column.as_str())
+                .id_source(("column_format", column))
+                .show(ui, |ui| {
+                    let id = ui.id().with("format");
+                    value_format_editor(ui, id, format, None);
+                    if ui.button("Remove").clicked() {
+                        removed = Some(column.clone());
+                    }
+                });
+        }
+        if let Some(column) = removed {
+            self.columns.remove(&column);
+        }
+        let draft_id = ui.id().with("column_format_draft");
+        let mut column = ui
+            .data(|data| data.get_temp::<String>(draft_id))
+            .unwrap_or_default();
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut column).hint_text("Column name"));
+            let add = egui::Button::new("Format Column");
+            if ui
+                .add_enabled(
+                    !column.is_empty() && !self.columns.contains_key(&column),
+                    add,
+                )
+                .clicked()
+            {
+                self.columns
+                    .insert(std::mem::take(&mut column), ValueFormat::default());
+            }
+        });
+        ui.data_mut(|data| data.insert_temp(draft_id, column));
+        None
+    }
+}
+
+/// Formats to copy the selected cells as, setting `copy` to the one picked.
+fn copy_menu(ui: &mut Ui, selection: Selection, copy: &mut Option<CopyFormat>) {
+    let in_list = selection.is_column().then_some(CopyFormat::InList);
+    for format in CopyFormat::ALL.into_iter().chain(in_list) {
+        if ui.button(format.name()).clicked() {
+            *copy = Some(format);
+            ui.close_menu();
+        }
+    }
+}
+
+/// Select `cell` when it's clicked or a drag starts on it, or extend the selection to it when it's
+/// shift-clicked or dragged over.
+fn select_cell(
+    ui: &mut Ui,
+    drag_id: Id,
+    dragging: bool,
+    selection: &mut Option<Selection>,
+    cell: Selection,
+) -> Response {
+    let (row, column) = cell.anchor;
+    let response = ui.interact(
+        ui.max_rect(),
+        drag_id.with((row, column, cell.whole_rows)),
+        egui::Sense::click_and_drag(),
+    );
+    if response.clicked() || response.drag_started() {
+        *selection = match *selection {
+            Some(current) if ui.input(|input| input.modifiers.shift) => {
+                Some(current.extend_to(row, column))
+            }
+            _ => Some(cell),
+        };
+        if response.drag_started() {
+            ui.data_mut(|data| data.insert_temp(drag_id, true));
+        }
+    } else if dragging && ui.rect_contains_pointer(ui.max_rect()) {
+        *selection = selection.map(|current| current.extend_to(row, column));
+    } else if response.secondary_clicked()
+        && !selection.is_some_and(|current| current.contains(row, column))
+    {
+        *selection = Some(cell);
+    }
+    response
+}
+
+impl Data {
+    /// Show the rows of the data as a table, with its values shown as `formats`.
+    pub fn show(&mut self, ui: &mut Ui, formats: &DisplayFormats) -> Option<Action> {
+        let style = &ui.style().clone();
+
+        let text_height = egui::TextStyle::Body.resolve(style).size;
+        // stop columns from getting too small to be usable
+        let min_col_width = style.spacing.text_edit_width / 2f32;
+        let margin_width = 2.0f32; // default separator width, keeps column resize on screen
+
+        // we put buttons in the header, so make sure that the vertical size of the header includes
+        // the button size and the normal padding around buttons
+        let header_height = style.spacing.interact_size.y + (2.0f32 * style.spacing.item_spacing.y);
+        let mut action: Option<Action> = match self.poll() {
+            Ok(false) => None,
+            Ok(true) => {
+                ui.ctx().request_repaint();
+                None
+            }
+            Err(err) => Some(Action::LogError(err)),
+        };
+        let schema = self.schema();
+        let (pinned, scrolled) = self.columns.visible(&schema);
+        // selections refer to columns by their position in the table, pinned columns first
+        let displayed = pinned.iter().chain(&scrolled).copied().collect_vec();
+        let value_formats = schema
+            .fields()
+            .iter()
+            .map(|field| formats.column(field.name(), field.data_type()))
+            .collect_vec();
+        let mut first_visible_row = None;
+        let inspector_id = ui.id().with("cell_inspector");
+        // for comparisons, the column each cell is checked against and the column of row statuses
+        let (counterparts, status_index) = match self.comparison() {
+            Some(comparison) => (
+                schema
+                    .fields()
+                    .iter()
+                    .map(|field| {
+                        comparison
+                            .counterpart(field.name())
+                            .and_then(|other| schema.index_of(&other).ok())
+                    })
+                    .collect_vec(),
+                schema.index_of(compare::STATUS_COLUMN).ok(),
+            ),
+            None => (vec![None; schema.fields().len()], None),
+        };
+
+        if let Some(comparison) = self.comparison() {
+            if let Some(filters) = show_comparison(ui, comparison, &self.filters) {
+                action = Some(Action::FilterData(filters));
+            }
+        }
+
+        // cells update a copy of the selection, which is applied once the table is laid out
+        let mut selection = self.selection;
+        let mut copy = None;
+        let drag_id = ui.id().with("selection_drag");
+        let dragging = if ui.input(|input| input.pointer.primary_down()) {
+            ui.data(|data| data.get_temp::<bool>(drag_id).unwrap_or_default())
+        } else {
+            ui.data_mut(|data| data.remove::<bool>(drag_id));
+            false
+        };
+        if let Some(copied) = self.take_copied() {
+            match copied {
+                Ok(text) => ui.ctx().output_mut(|output| output.copied_text = text),
+                Err(err) => action = Some(Action::LogError(err)),
+            }
+        }
+        // keys only apply to the selection while no text field has focus
+        if selection.is_some() && ui.memory(|memory| memory.focused().is_none()) {
+            ui.input(|input| {
+                if input
+                    .events
+                    .iter()
+                    .any(|event| matches!(event, egui::Event::Copy))
+                {
+                    copy = Some(CopyFormat::Tsv);
+                }
+                if input.key_pressed(Key::Escape) {
+                    selection = None;
+                }
+            });
+        }
+        ui.horizontal_wrapped(|ui| {
+            if ui.button("Export").clicked() {
+                action = Some(Action::ShowPopover(Box::new(ExportData::new(
+                    self.dataframe(),
+                    self.row_count(),
+                ))));
+            }
+            if let Some(field) = schema.fields().first() {
+                if ui
+                    .button("Chart")
+                    .on_hover_text("Right click a column to chart it")
+                    .clicked()
+                {
+                    action = Some(Action::ChartColumn(field.name().clone()));
+                }
+            }
+            if ui.button("Save as Table").clicked() {
+                action = Some(Action::ShowPopover(Box::new(SaveTable::new(
+                    self.dataframe(),
+                    self.view_sql(),
+                ))));
+            }
+            if let Some(columns) = column_chooser(ui, &self.columns, &schema) {
+                action = Some(Action::ArrangeColumns(columns));
+            }
+            if let Some(selected) = selection {
+                ui.separator();
+                ui.menu_button("Copy", |ui| copy_menu(ui, selected, &mut copy))
+                    .response
+                    .on_hover_text("Ctrl+C copies as TSV");
+                if ui.small_button("Clear selection").clicked() {
+                    selection = None;
+                }
+            }
+            if !self.filters.is_empty() {
+                ui.separator();
+            }
+            for (i, filter) in self.filters.iter().enumerate() {
+                let chip = egui::Button::new(format!("{} ✖", filter))
+                    .small()
+                    .rounding(ui.style().spacing.interact_size.y / 2.0);
+                if ui.add(chip).on_hover_text("Remove filter").clicked() {
+                    let mut filters = self.filters.clone();
+                    filters.remove(i);
+                    action = Some(Action::FilterData(filters));
+                }
+            }
+            if self.filters.len() > 1 && ui.small_button("Clear filters").clicked() {
+                action = Some(Action::FilterData(vec![]));
+            }
+            if self.is_running() {
+                ui.separator();
+                ui.spinner();
+                if ui
+                    .small_button("Cancel")
+                    .on_hover_text("Stop reading and counting rows")
+                    .clicked()
+                {
+                    self.cancel();
+                    action = Some(Action::LogError(anyhow::anyhow!("Query cancelled")));
+                }
+            }
+        });
+
+        // the pinned and scrolled parts of the table scroll down together, by moving a part to
+        // `follow` when it differs. Returns the id of the part's scroll state, and the offset it
+        // was drawn at, which egui only moves by this frame's scrolling once it has been drawn.
+        let mut show_table = |ui: &mut Ui,
+                              indices: &[usize],
+                              first_position: usize,
+                              pinned: bool,
+                              follow: Option<f32>| {
+            // the id egui_extras keeps the table's scroll position under
+            let scroll_id = ui.make_persistent_id(Id::new("scroll_area"));
+            let offset = scroll_offset(ui.ctx(), scroll_id);
+            let mut table = TableBuilder::new(ui)
+                .striped(true)
+                .auto_shrink([pinned, false])
+                .max_scroll_height(f32::INFINITY);
+            let drawn = match (follow, offset) {
+                (Some(follow), Some(offset)) if (offset - follow).abs() <= 0.5 => offset,
+                (Some(follow), _) => {
+                    table = table.vertical_scroll_offset(follow);
+                    follow
+                }
+                (None, offset) => offset.unwrap_or_default(),
+            };
+            if pinned {
+                table = table
+                    .scroll_bar_visibility(ScrollBarVisibility::AlwaysHidden)
+                    // row numbers, which select whole rows
+                    .column(Column::auto().resizable(false));
+            }
+            table = table.columns(
+                Column::auto()
+                    .at_least(min_col_width)
+                    .clip(true)
+                    .resizable(true),
+                indices.len(),
+            );
+            if !pinned {
+                table = table.column(
+                    Column::remainder()
+                        .at_least(margin_width)
+                        .resizable(false)
+                        .clip(false),
+                );
+            }
+            table
+                .resizable(true)
+                .header(header_height, |mut header| {
+                    if pinned {
+                        header.col(|ui| {
+                            ui.weak("#");
+                        });
+                    }
+                    for (position, &col_index) in (first_position..).zip(indices) {
+                        let field = schema.field(col_index);
+                        header.col(|ui| {
+                            let column_name = field.name().to_string();
+                            let priority =
+                                self.sort.iter().position(|key| key.column == column_name);
+                            let mut sort_state =
+                                priority.map_or(SortState::NotSorted, |priority| {
+                                    self.sort[priority].direction
+                                });
+                            let draft_id = ui.id().with("filter_draft");
+                            ui.horizontal_centered(|ui| {
+                                let response = ui
+                                    .multi_state_button(&mut sort_state, &column_name)
+                                    .on_hover_text(
+                                        "Shift-click to add to the sort, right click to profile and for more options",
+                                    );
+                                if response.clicked() {
+                                    let extend = ui.input(|input| input.modifiers.shift);
+                                    action = Some(Action::SortData(with_sort_key(
+                                        &self.sort,
+                                        &column_name,
+                                        sort_state,
+                                        extend,
+                                    )));
+                                }
+                                if let (Some(priority), true) = (priority, self.sort.len() > 1) {
+                                    ui.weak((priority + 1).to_string())
+                                        .on_hover_text("Sort priority");
+                                }
+                                response.context_menu(|ui| {
+                                    if let Some(sort) = sort_menu(ui, &self.sort, &column_name) {
+                                        action = Some(Action::SortData(sort));
+                                        ui.close_menu();
+                                    }
+                                    ui.separator();
+                                    if ui.button("Profile").clicked() {
+                                        action = Some(Action::ProfileColumn(column_name.clone()));
+                                        ui.close_menu();
+                                    }
+                                    if ui.button("Chart").clicked() {
+                                        action = Some(Action::ChartColumn(column_name.clone()));
+                                        ui.close_menu();
+                                    }
+                                    ui.separator();
+                                    let column = Selection::column(position);
+                                    if ui.button("Select column").clicked() {
+                                        selection = Some(column);
+                                        ui.close_menu();
+                                    }
+                                    ui.menu_button("Copy column as", |ui| {
+                                        copy_menu(ui, column, &mut copy);
+                                        if copy.is_some() {
+                                            selection = Some(column);
+                                        }
+                                    });
+                                    ui.separator();
+                                    let mut columns = self.columns.clone();
+                                    let pin = if pinned { "Unpin" } else { "Pin to the left" };
+                                    if ui.button(pin).clicked() {
+                                        columns.set_pinned(&column_name, !pinned);
+                                        ui.close_menu();
+                                    }
+                                    if ui.button("Hide").clicked() {
+                                        columns.set_hidden(&column_name, true);
+                                        ui.close_menu();
+                                    }
+                                    if columns != self.columns {
+                                        action = Some(Action::ArrangeColumns(columns));
+                                    }
+                                });
+                                let mut icon = egui::RichText::new("🔍");
+                                if self
+                                    .filters
+                                    .iter()
+                                    .any(|filter| filter.column == column_name)
+                                {
+                                    icon = icon.color(ui.style().visuals.selection.stroke.color);
+                                }
+                                ui.menu_button(icon, |ui| {
+                                    if let Some(filters) = filter_menu(ui, self, field, draft_id)
+                                    {
+                                        action = Some(Action::FilterData(filters));
+                                    }
+                                });
+                            });
+                        });
+                    }
+                })
+                .body(|body| {
+                    body.rows(text_height, self.num_rows(), |mut row| {
+                        let index = row.index();
+                        first_visible_row.get_or_insert(index);
+                        // rows of pages that are still being fetched are left blank
+                        let page = self.row(index);
+                        if pinned {
+                            row.col(|ui| {
+                                let row_selection = Selection::row(index);
+                                let response = select_cell(
+                                    ui,
+                                    drag_id,
+                                    dragging,
+                                    &mut selection,
+                                    row_selection,
+                                );
+                                ui.add(
+                                    egui::Label::new(
+                                        egui::RichText::new((index + 1).to_string()).weak(),
+                                    )
+                                    .selectable(false),
+                                );
+                                if let Some(selected) = selection {
+                                    response.context_menu(|ui| copy_menu(ui, selected, &mut copy));
+                                }
+                            });
+                        }
+                        for (position, &col_index) in (first_position..).zip(indices) {
+                            let field = schema.field(col_index);
+                            row.col(|ui| {
+                                if selection
+                                    .is_some_and(|selected| selected.contains(index, position))
+                                {
+                                    ui.painter().rect_filled(
+                                        ui.max_rect(),
+                                        0.0,
+                                        ui.visuals().selection.bg_fill,
+                                    );
+                                }
+                                let cell = Selection::cell(index, position);
+                                let response =
+                                    select_cell(ui, drag_id, dragging, &mut selection, cell);
+                                if let Some(selected) = selection {
+                                    response.context_menu(|ui| copy_menu(ui, selected, &mut copy));
+                                }
+                                // while not efficient (as noted in docs) we need to display
+                                // at most a few dozen records at a time (barring pathological
+                                // tables with absurd numbers of columns) and should still
+                                // have conversion times on the order of ns.
+                                // TODO: have separate value layout function
+                                ui.with_layout(
+                                    if is_integer(field.data_type()) {
+                                        egui::Layout::centered_and_justified(
+                                            egui::Direction::LeftToRight,
+                                        )
+                                    } else if is_float(field.data_type()) {
+                                        egui::Layout::right_to_left(egui::Align::Center)
+                                    } else {
+                                        egui::Layout::left_to_right(egui::Align::Center)
+                                    }
+                                    .with_main_wrap(false),
+                                    |ui| {
+                                        if let Some((batch, offset)) = &page {
+                                            let column = batch.column(col_index);
+                                            let value = format_value(
+                                                column,
+                                                *offset,
+                                                value_formats[col_index],
+                                            );
+                                            // compared as stored, as each side may be formatted
+                                            // differently
+                                            let changed =
+                                                match (counterparts[col_index], status_index) {
+                                                    (Some(other), Some(status)) => {
+                                                        array_value_to_string(
+                                                            batch.column(status),
+                                                            *offset,
+                                                        )
+                                                        .is_ok_and(|status| {
+                                                            status == compare::CHANGED
+                                                        }) && array_value_to_string(
+                                                            batch.column(other),
+                                                            *offset,
+                                                        )
+                                                        .ok()
+                                                            != array_value_to_string(
+                                                                column, *offset,
+                                                            )
+                                                            .ok()
+                                                    }
+                                                    _ => false,
+                                                };
+                                            if changed {
+                                                ui.painter().rect_filled(
+                                                    ui.max_rect(),
+                                                    0.0,
+                                                    ui.visuals().warn_fg_color.gamma_multiply(0.3),
+                                                );
+                                            }
+                                            if field.data_type().is_nested() {
+                                                let response = ui
+                                                    .add(
+                                                        egui::Label::new(value)
+                                                            .sense(egui::Sense::click()),
+                                                    )
+                                                    .on_hover_text("Click to inspect");
+                                                if response.clicked() {
+                                                    action = Some(Action::InspectCell(
+                                                        CellInspector::new(
+                                                            inspector_id,
+                                                            field,
+                                                            index,
+                                                            batch,
+                                                            *offset,
+                                                        ),
+                                                    ));
+                                                }
+                                            } else if column.is_null(*offset) {
+                                                ui.add(
+                                                    egui::Label::new(
+                                                        egui::RichText::new(&formats.null)
+                                                            .weak()
+                                                            .italics(),
+                                                    )
+                                                    .selectable(false),
+                                                );
+                                            } else {
+                                                // dragging selects cells rather than text
+                                                ui.add(egui::Label::new(value).selectable(false));
+                                            }
+                                        }
+                                    },
+                                );
+                            });
+                        }
+                        if !pinned {
+                            row.col(|_ui| {
+                                // make last empty column to take up remaining space
+                            });
+                        }
+                    });
+                });
+            (scroll_id, drawn)
+        };
+        // the scrolled part is drawn first, beside where the pinned part was last frame, so that the
+        // pinned part can be drawn at the same offset. The scrolled part follows the pinned part
+        // when that was scrolled since it was last drawn.
+        let pinned_id = ui.id().with("pinned_columns");
+        let (pinned_width, pinned_scroll_id, pinned_drawn) = ui
+            .data(|data| data.get_temp::<(f32, Id, f32)>(pinned_id))
+            .unwrap_or((0.0, Id::NULL, 0.0));
+        let pinned_scrolled = scroll_offset(ui.ctx(), pinned_scroll_id)
+            .filter(|offset| (offset - pinned_drawn).abs() > 0.5);
+        let rect = ui.available_rect_before_wrap();
+        let (_, scrolled_drawn) = ui
+            .allocate_ui_at_rect(rect.with_min_x(rect.left() + pinned_width), |ui| {
+                // named, as it's in a child of the same id as the pinned part's table
+                egui::ScrollArea::horizontal()
+                    .id_source("scrolled_columns")
+                    .show(ui, |ui| {
+                        show_table(ui, &scrolled, pinned.len(), false, pinned_scrolled)
+                    })
+                    .inner
+            })
+            .inner;
+        let pinned_part = ui.allocate_ui_at_rect(rect, |ui| {
+            show_table(ui, &pinned, 0, true, Some(scrolled_drawn))
+        });
+        let (pinned_scroll_id, pinned_drawn) = pinned_part.inner;
+        let pinned_width = pinned_part.response.rect.width();
+        ui.data_mut(|data| {
+            data.insert_temp(pinned_id, (pinned_width, pinned_scroll_id, pinned_drawn))
+        });
+        if let Some(index) = first_visible_row {
+            self.evict_pages(index);
+        }
+        self.selection = selection;
+        if let Some(format) = copy {
+            self.copy_selection(format, &displayed);
+        }
+        action
+    }
+}
+
+impl Show for Schema {
+    fn show(&self, ui: &mut Ui) -> Option<Action> {
+        ui.collapsing("Schema", |ui| {
+            for field in self.fields.iter() {
+                ui.label(format!("{}: {}", field.name(), field.data_type()));
+            }
+        });
+        ui.collapsing("Metadata", |ui| {
+            for (key, value) in self.metadata.iter() {
+                if let Ok(json) = serde_json::from_str::<Value>(value) {
+                    JsonTree::new(key, &json).show(ui);
+                } else {
+                    ui.label(format!("{}: {}", key, value));
+                }
+            }
+        });
+        None
+    }
+}
+
+impl Show for ParquetFiles {
+    fn show(&self, ui: &mut Ui) -> Option<Action> {
+        for (path, metadata) in self.iter() {
+            let file = metadata.file_metadata();
+            egui::CollapsingHeader::new(path)
+                .default_open(self.len() == 1)
+                .show(ui, |ui| {
+                    egui::Grid::new((path, "file"))
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            ui.label("Rows");
+                            ui.label(file.num_rows().to_string());
+                            ui.end_row();
+                            ui.label("Row Groups");
+                            ui.label(metadata.num_row_groups().to_string());
+                            ui.end_row();
+                            ui.label("Format Version");
+                            ui.label(file.version().to_string());
+                            ui.end_row();
+                            ui.label("Created By");
+                            ui.label(file.created_by().unwrap_or_default());
+                            ui.end_row();
+                        });
+
+                    for (i, row_group) in metadata.row_groups().iter().enumerate() {
+                        let title = format!(
+                            "Row Group {}: {} rows, {} ({} uncompressed)",
+                            i,
+                            row_group.num_rows(),
+                            format_bytes(row_group.compressed_size()),
+                            format_bytes(row_group.total_byte_size()),
+                        );
+                        egui::CollapsingHeader::new(title)
+                            .id_source((path, i))
+                            .show(ui, |ui| {
+                                egui::Grid::new((path, i, "columns")).striped(true).show(
+                                    ui,
+                                    |ui| {
+                                        for heading in [
+                                            "Column",
+                                            "Type",
+                                            "Codec",
+                                            "Encodings",
+                                            "Compressed",
+                                            "Uncompressed",
+                                            "Min",
+                                            "Max",
+                                            "Nulls",
+                                            "Pages",
+                                            "Page Index",
+                                            "Bloom Filter",
+                                        ] {
+                                            ui.strong(heading);
+                                        }
+                                        ui.end_row();
+
+                                        for (j, chunk) in row_group.columns().iter().enumerate() {
+                                            let (min, max) = chunk
+                                                .statistics()
+                                                .map(format_statistics)
+                                                .unwrap_or_default();
+                                            // only populated when the file has an offset index
+                                            let pages = metadata
+                                                .offset_index()
+                                                .and_then(|index| index.get(i)?.get(j))
+                                                .map_or("".to_owned(), |pages| {
+                                                    pages.len().to_string()
+                                                });
+                                            let has_page_index = chunk
+                                                .column_index_offset()
+                                                .and(chunk.offset_index_offset())
+                                                .is_some();
+
+                                            ui.label(chunk.column_path().string());
+                                            ui.label(chunk.column_type().to_string());
+                                            ui.label(chunk.compression().to_string());
+                                            ui.label(chunk.encodings().iter().join(", "));
+                                            ui.label(format_bytes(chunk.compressed_size()));
+                                            ui.label(format_bytes(chunk.uncompressed_size()));
+                                            ui.label(min);
+                                            ui.label(max);
+                                            ui.label(
+                                                chunk.statistics().map_or("".to_owned(), |stats| {
+                                                    stats.null_count().to_string()
+                                                }),
+                                            );
+                                            ui.label(pages);
+                                            ui.label(format_flag(has_page_index));
+                                            ui.label(format_flag(
+                                                chunk.bloom_filter_offset().is_some(),
+                                            ));
+                                            ui.end_row();
+                                        }
+                                    },
+                                );
+                            });
+                    }
+                });
+        }
+        None
+    }
+}
+
+fn format_flag(flag: bool) -> &'static str {
+    if flag {
+        "✔"
+    } else {
+        "✖"
+    }
+}
+
+pub fn format_bytes(bytes: i64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} {}", bytes, UNITS[0]),
+        _ => format!("{:.1} {}", value, UNITS[unit]),
+    }
+}
+
+fn format_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Format the min and max of column chunk statistics, using the physical type of the column.
+pub fn format_statistics(stats: &Statistics) -> (String, String) {
+    if !stats.has_min_max_set() {
+        return Default::default();
+    }
+    match stats {
+        Statistics::Boolean(stats) => (stats.min().to_string(), stats.max().to_string()),
+        Statistics::Int32(stats) => (stats.min().to_string(), stats.max().to_string()),
+        Statistics::Int64(stats) => (stats.min().to_string(), stats.max().to_string()),
+        Statistics::Int96(stats) => (stats.min().to_string(), stats.max().to_string()),
+        Statistics::Float(stats) => (stats.min().to_string(), stats.max().to_string()),
+        Statistics::Double(stats) => (stats.min().to_string(), stats.max().to_string()),
+        Statistics::ByteArray(stats) => {
+            // most byte arrays are strings, fall back to hex for anything else
+            let format = |value: &datafusion::parquet::data_type::ByteArray| {
+                value
+                    .as_utf8()
+                    .map_or_else(|_| format_hex(value.data()), str::to_owned)
+            };
+            (format(stats.min()), format(stats.max()))
+        }
+        Statistics::FixedLenByteArray(stats) => (
+            format_hex(stats.min().data()),
+            format_hex(stats.max().data()),
+        ),
+    }
+}
+
+trait EditableLabel {
+    fn editable_label(&mut self, id: Id, label: &str) -> Option<String>;
+}
+
+impl EditableLabel for Ui {
+    fn editable_label(&mut self, id: Id, label: &str) -> Option<String> {
+        type State = Arc<Mutex<String>>;
+
+        let current_label: Option<State> = self.memory_mut(|mem| mem.data.get_temp(id).clone());
+        match current_label.as_ref() {
             Some(label) => {
                 let mut label = label
                     .lock()
@@ -372,10 +3300,29 @@ impl EditableLabel for Ui {
     }
 }
 
-impl Show for DataSourceListing {
+/// Files of a source whose schema differs from the merged schema, and how.
+fn show_schema_report(ui: &mut Ui, table_name: &str, report: &[FileSchemaDiff]) {
+    egui::CollapsingHeader::new(format!("⚠ {} files differ", report.len()))
+        .id_source(("schema_report", table_name))
+        .show(ui, |ui| {
+            for diff in report {
+                ui.label(&diff.file);
+                ui.indent(&diff.file, |ui| {
+                    if !diff.missing.is_empty() {
+                        ui.weak(format!("missing {}", diff.missing.join(", ")));
+                    }
+                    for (column, data_type) in &diff.widened {
+                        ui.weak(format!("{} stored as {}", column, data_type));
+                    }
+                });
+            }
+        });
+}
+
+impl Show for DataSource {
     fn show(&self, ui: &mut Ui) -> Option<Action> {
         let mut action = None;
-        for (table_name, table_definition) in self.iter().sorted_by_key(|x| x.0) {
+        for (table_name, table_definition) in self.tables().iter().sorted_by_key(|x| x.0) {
             egui::collapsing_header::CollapsingState::load_with_default_open(
                 ui.ctx(),
                 format!("{} data source listing", table_name).into(),
@@ -385,20 +3332,44 @@ impl Show for DataSourceListing {
                 if let Some(rename) = ui.editable_label(table_name.to_owned().into(), table_name) {
                     action = Some(Action::RenameSource((table_name.to_owned(), rename)));
                 }
+                if let Some(result_table) = self.result_table(table_name) {
+                    ui.weak(result_table.name())
+                        .on_hover_text("Saved from a query result");
+                }
                 if ui.small_button("✖").clicked() {
                     action = Some(Action::DeleteSource(table_name.to_owned()));
                 }
             })
             .body(|ui| {
                 table_definition.schema().show(ui);
-                if ui.button("Load").clicked() {
-                    action = Some(Action::QuerySource(Query::TableName(table_name.to_owned())));
+                if let Some(report) = self.schema_report(table_name) {
+                    show_schema_report(ui, table_name, report);
                 }
+                ui.horizontal(|ui| {
+                    if ui.button("Load").clicked() {
+                        action = Some(Action::QuerySource(Query::TableName(table_name.to_owned())));
+                    }
+                    if is_parquet(table_definition.as_ref()) && ui.button("File Details").clicked()
+                    {
+                        action = Some(Action::InspectSource(table_name.to_owned()));
+                    }
+                });
             });
         }
-        if ui.button("Add Source").clicked() {
-            action = Some(Action::ShowPopover(Box::<AddDataSource>::default()));
-        }
+        ui.horizontal(|ui| {
+            if ui.button("Add Source").clicked() {
+                action = Some(Action::ShowPopover(Box::<AddDataSource>::default()));
+            }
+            if ui
+                .add_enabled(!self.tables().is_empty(), egui::Button::new("Compare"))
+                .on_hover_text("List the rows that differ between two tables")
+                .clicked()
+            {
+                action = Some(Action::ShowPopover(Box::new(CompareTables::new(
+                    self.tables(),
+                ))));
+            }
+        });
         action
     }
 }
@@ -416,6 +3387,11 @@ fn is_float(t: &DataType) -> bool {
     matches!(t, Float32 | Float64)
 }
 
+fn is_binary(t: &DataType) -> bool {
+    use DataType::*;
+    matches!(t, Binary | LargeBinary | FixedSizeBinary(_) | BinaryView)
+}
+
 pub trait SelectionDepth {
     // TODO: https://stackoverflow.com/questions/25867875/how-do-i-toggle-through-enum-variants
     fn inc(&self) -> Self;
@@ -1,18 +1,55 @@
-use datafusion::arrow::compute::concat_batches;
-use datafusion::arrow::datatypes::Schema;
+use crate::clipboard::{format_selection, CopyFormat, Selection, MAX_COPY_ROWS};
+use crate::columns::ColumnLayout;
+use crate::compare::Comparison;
+use crate::merge::{merge_schemas, FileSchemaDiff};
+use async_compat::Compat;
+use datafusion::arrow::array::StringArray;
+use datafusion::arrow::compute::{cast_with_options, concat_batches, CastOptions};
+use datafusion::arrow::datatypes::{DataType, Schema, SchemaRef};
 use datafusion::arrow::record_batch::RecordBatch;
-use datafusion::datasource::TableProvider;
+use datafusion::arrow::util::display::array_value_to_string;
+use datafusion::common::tree_node::TreeNodeRecursion;
+use datafusion::common::Column;
+use datafusion::datasource::file_format::options::{ArrowReadOptions, ReadOptions};
+use datafusion::datasource::file_format::parquet::ParquetFormat;
+use datafusion::datasource::listing::{
+    ListingOptions, ListingTable, ListingTableConfig, ListingTableUrl,
+};
+use datafusion::datasource::{MemTable, TableProvider};
 use datafusion::execution::config::SessionConfig;
-use datafusion::logical_expr::col as col_expr;
-use datafusion::prelude::{ParquetReadOptions, SessionContext};
+use datafusion::execution::context::SessionState;
+use datafusion::functions::core::expr_fn::get_field;
+use datafusion::logical_expr::{
+    binary_expr, cast, AggregateFunction, BuiltInWindowFunction, Expr, LogicalPlan, Operator,
+};
+use datafusion::parquet::arrow::async_reader::{AsyncFileReader, ParquetObjectReader};
+use datafusion::parquet::file::metadata::ParquetMetaData;
+use datafusion::physical_plan::SendableRecordBatchStream;
+use datafusion::prelude::{
+    ident, lit, AvroReadOptions, CsvReadOptions, DataFrame, NdJsonReadOptions, ParquetReadOptions,
+    SessionContext,
+};
+use itertools::Itertools;
+use object_store::aws::{AmazonS3Builder, AmazonS3ConfigKey};
 use object_store::azure::MicrosoftAzureBuilder;
+use object_store::gcp::GoogleCloudStorageBuilder;
+use object_store::http::HttpBuilder;
 use object_store::local::LocalFileSystem;
+use object_store::{ClientConfigKey, ObjectStore};
 use regex::Regex;
-use smol::future::Boxed;
+use serde::{Deserialize, Serialize};
+use smol::future::{Boxed, FutureExt};
+use smol::stream::StreamExt;
+use smol::{Task, Timer};
 use std::borrow::Borrow;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::mem;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use strum::IntoEnumIterator;
 use url::Url;
 
 use anyhow::anyhow;
@@ -20,6 +57,17 @@ use anyhow::anyhow;
 pub type DataResult = anyhow::Result<Data>;
 pub type DataFuture = Boxed<DataResult>;
 pub type DataSourceListing = BTreeMap<String, Arc<dyn TableProvider>>;
+/// Footer metadata, including page indexes, of each file in a parquet source.
+pub type ParquetFiles = Vec<(String, Arc<ParquetMetaData>)>;
+
+/// Number of rows fetched from the query at a time as the table is scrolled.
+pub const PAGE_SIZE: usize = 1024;
+/// Pages kept in memory; pages furthest from the visible rows are dropped first.
+const MAX_CACHED_PAGES: usize = 16;
+/// Columns with at most this many distinct values can be filtered by picking from a list.
+pub const MAX_PICK_VALUES: usize = 100;
+/// Files checked for key=value directories when detecting the partitions of a directory source.
+const PARTITION_SAMPLE_FILES: usize = 10;
 
 const UNC_REGEX: &str = r"\\\\\?\\UNC\\([A-Za-z0-9_.$●-]+)\\([A-Za-z0-9_.$●-]+)\\";
 
@@ -30,24 +78,458 @@ pub enum SortState {
     Descending,
 }
 
-#[derive(Clone, Debug)]
+/// One column of a multi-column sort, in order of priority.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct SortKey {
+    pub column: String,
+    pub direction: SortState,
+    pub nulls_first: bool,
+}
+
+impl SortKey {
+    pub fn new(column: &str, direction: SortState) -> Self {
+        Self {
+            column: column.to_owned(),
+            direction,
+            // consider null "greater" than real values, so they sort last unless descending
+            nulls_first: direction == SortState::Descending,
+        }
+    }
+
+    fn to_expr(&self) -> Option<Expr> {
+        match self.direction {
+            SortState::NotSorted => None,
+            direction => {
+                Some(ident(&self.column).sort(direction == SortState::Ascending, self.nulls_first))
+            }
+        }
+    }
+
+    fn to_sql(&self) -> Option<String> {
+        let direction = match self.direction {
+            SortState::NotSorted => return None,
+            SortState::Ascending => "ASC",
+            SortState::Descending => "DESC",
+        };
+        let nulls = if self.nulls_first { "FIRST" } else { "LAST" };
+        Some(format!(
+            "{} {} NULLS {}",
+            quote_identifier(&self.column),
+            direction,
+            nulls
+        ))
+    }
+}
+
+/// A condition on a single column, applied on top of the query like a sort.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ColumnFilter {
+    /// Case insensitive substring match.
+    Contains(String),
+    Regex(String),
+    /// Inclusive bounds, parsed as the column's type.
+    Range {
+        min: Option<String>,
+        max: Option<String>,
+    },
+    IsNull,
+    IsNotNull,
+    /// Any of the listed values, parsed as the column's type.
+    OneOf(Vec<String>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Filter {
+    pub column: String,
+    pub data_type: DataType,
+    pub filter: ColumnFilter,
+}
+
+impl Filter {
+    pub fn new(column: &str, data_type: &DataType, filter: ColumnFilter) -> Self {
+        Self {
+            column: column.to_owned(),
+            data_type: data_type.clone(),
+            filter,
+        }
+    }
+
+    /// Literal for a value entered as text, checking now that it can be read as the column's
+    /// type rather than failing when the query runs.
+    fn literal(&self, value: &str) -> anyhow::Result<Expr> {
+        cast_with_options(
+            &StringArray::from(vec![value]),
+            &self.data_type,
+            &CastOptions {
+                safe: false,
+                ..Default::default()
+            },
+        )
+        .map_err(|err| anyhow!("Invalid value for {}: {}", self.column, err))?;
+        Ok(cast(lit(value), self.data_type.clone()))
+    }
+
+    pub fn to_expr(&self) -> anyhow::Result<Expr> {
+        let column = ident(&self.column);
+        Ok(match &self.filter {
+            // LIKE doesn't support escaping wildcards, so match the literal text as a regex
+            ColumnFilter::Contains(text) => {
+                binary_expr(column, Operator::RegexIMatch, lit(regex::escape(text)))
+            }
+            ColumnFilter::Regex(pattern) => {
+                Regex::new(pattern)?;
+                binary_expr(column, Operator::RegexMatch, lit(pattern.as_str()))
+            }
+            ColumnFilter::Range { min, max } => {
+                let mut conditions = vec![];
+                if let Some(min) = min {
+                    conditions.push(column.clone().gt_eq(self.literal(min)?));
+                }
+                if let Some(max) = max {
+                    conditions.push(column.clone().lt_eq(self.literal(max)?));
+                }
+                conditions
+                    .into_iter()
+                    .reduce(Expr::and)
+                    .ok_or(anyhow!("Range filter on {} has no bounds", self.column))?
+            }
+            ColumnFilter::IsNull => column.is_null(),
+            ColumnFilter::IsNotNull => column.is_not_null(),
+            ColumnFilter::OneOf(values) => column.in_list(
+                values
+                    .iter()
+                    .map(|value| self.literal(value))
+                    .collect::<anyhow::Result<_>>()?,
+                false,
+            ),
+        })
+    }
+}
+
+/// `value` as an SQL string literal.
+fn sql_string(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+impl Filter {
+    /// The filter as an SQL condition, matching [`Filter::to_expr`].
+    fn to_sql(&self) -> anyhow::Result<String> {
+        let column = quote_identifier(&self.column);
+        // checked the same way as the literals of the expression
+        let literal = |value: &str| -> anyhow::Result<String> {
+            self.literal(value)?;
+            Ok(format!(
+                "arrow_cast({}, {})",
+                sql_string(value),
+                sql_string(&self.data_type.to_string())
+            ))
+        };
+        Ok(match &self.filter {
+            ColumnFilter::Contains(text) => {
+                format!("{} ~* {}", column, sql_string(&regex::escape(text)))
+            }
+            ColumnFilter::Regex(pattern) => {
+                Regex::new(pattern)?;
+                format!("{} ~ {}", column, sql_string(pattern))
+            }
+            ColumnFilter::Range { min, max } => {
+                let mut conditions = vec![];
+                if let Some(min) = min {
+                    conditions.push(format!("{} >= {}", column, literal(min)?));
+                }
+                if let Some(max) = max {
+                    conditions.push(format!("{} <= {}", column, literal(max)?));
+                }
+                if conditions.is_empty() {
+                    return Err(anyhow!("Range filter on {} has no bounds", self.column));
+                }
+                conditions.join(" AND ")
+            }
+            ColumnFilter::IsNull => format!("{} IS NULL", column),
+            ColumnFilter::IsNotNull => format!("{} IS NOT NULL", column),
+            ColumnFilter::OneOf(values) => format!(
+                "{} IN ({})",
+                column,
+                values
+                    .iter()
+                    .map(|value| literal(value))
+                    .collect::<anyhow::Result<Vec<_>>>()?
+                    .join(", ")
+            ),
+        })
+    }
+}
+
+impl Display for Filter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.filter {
+            ColumnFilter::Contains(text) => write!(f, "{} contains \"{}\"", self.column, text),
+            ColumnFilter::Regex(pattern) => write!(f, "{} ~ /{}/", self.column, pattern),
+            ColumnFilter::Range {
+                min: Some(min),
+                max: Some(max),
+            } => write!(f, "{} ≤ {} ≤ {}", min, self.column, max),
+            ColumnFilter::Range { min: Some(min), .. } => write!(f, "{} ≥ {}", self.column, min),
+            ColumnFilter::Range { max: Some(max), .. } => write!(f, "{} ≤ {}", self.column, max),
+            ColumnFilter::Range { .. } => write!(f, "{}", self.column),
+            ColumnFilter::IsNull => write!(f, "{} is null", self.column),
+            ColumnFilter::IsNotNull => write!(f, "{} is not null", self.column),
+            ColumnFilter::OneOf(values) => {
+                write!(f, "{} in ({})", self.column, values.iter().join(", "))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Query {
     TableName(String),
     Sql(String),
 }
 
+impl Query {
+    /// The query as a single SQL statement.
+    pub fn sql(&self) -> String {
+        match self {
+            Query::TableName(table) => {
+                format!("SELECT * FROM {}", quote_identifier(&table.to_lowercase()))
+            }
+            Query::Sql(sql) => sql.trim().trim_end_matches(';').trim_end().to_owned(),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum TableFormat {
+    Parquet,
+    Csv,
+    Json,
+    Avro,
+    Arrow,
+}
+
+impl TableFormat {
+    pub const ALL: [TableFormat; 5] = [
+        TableFormat::Parquet,
+        TableFormat::Csv,
+        TableFormat::Json,
+        TableFormat::Avro,
+        TableFormat::Arrow,
+    ];
+
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.trim_start_matches('.').to_lowercase().as_str() {
+            "parquet" | "parq" | "pq" => Some(TableFormat::Parquet),
+            "csv" | "tsv" => Some(TableFormat::Csv),
+            "json" | "ndjson" | "jsonl" => Some(TableFormat::Json),
+            "avro" => Some(TableFormat::Avro),
+            "arrow" | "arrows" | "feather" | "ipc" => Some(TableFormat::Arrow),
+            _ => None,
+        }
+    }
+
+    /// Extension used to find files when loading a directory without an explicit extension.
+    pub fn default_extension(&self) -> &'static str {
+        match self {
+            TableFormat::Parquet => ".parquet",
+            TableFormat::Csv => ".csv",
+            TableFormat::Json => ".json",
+            TableFormat::Avro => ".avro",
+            TableFormat::Arrow => ".arrow",
+        }
+    }
+
+    /// Whether the schemas of files can be merged, which needs a reader that finds columns by
+    /// name. The other readers take columns by position in the file.
+    pub fn can_merge_schemas(&self) -> bool {
+        matches!(self, TableFormat::Parquet | TableFormat::Json)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TableFormat::Parquet => "Parquet",
+            TableFormat::Csv => "CSV",
+            TableFormat::Json => "NDJSON",
+            TableFormat::Avro => "Avro",
+            TableFormat::Arrow => "Arrow IPC",
+        }
+    }
+}
+
+/// Types a partition column can be declared as. Values are parsed from the directory names.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum PartitionType {
+    Utf8,
+    Int64,
+    Float64,
+    Boolean,
+    Date,
+}
+
+impl PartitionType {
+    pub const ALL: [PartitionType; 5] = [
+        PartitionType::Utf8,
+        PartitionType::Int64,
+        PartitionType::Float64,
+        PartitionType::Boolean,
+        PartitionType::Date,
+    ];
+
+    pub fn data_type(&self) -> DataType {
+        match self {
+            PartitionType::Utf8 => DataType::Utf8,
+            PartitionType::Int64 => DataType::Int64,
+            PartitionType::Float64 => DataType::Float64,
+            PartitionType::Boolean => DataType::Boolean,
+            PartitionType::Date => DataType::Date32,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PartitionType::Utf8 => "String",
+            PartitionType::Int64 => "Integer",
+            PartitionType::Float64 => "Float",
+            PartitionType::Boolean => "Boolean",
+            PartitionType::Date => "Date",
+        }
+    }
+}
+
+/// How a result saved as a table is read by later queries.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ResultTable {
+    /// Runs the result's query each time the table is read.
+    View,
+    /// Keeps the rows of the result in memory, as they were when it was saved.
+    Snapshot,
+}
+
+impl ResultTable {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ResultTable::View => "view",
+            ResultTable::Snapshot => "snapshot",
+        }
+    }
+
+    /// A table reading the result of `df`. Snapshots run the whole query to keep its rows.
+    pub async fn table(
+        &self,
+        df: DataFrame,
+        timeout: Option<Duration>,
+    ) -> anyhow::Result<Arc<dyn TableProvider>> {
+        Ok(match self {
+            ResultTable::View => df.into_view(),
+            ResultTable::Snapshot => {
+                let schema: SchemaRef = Arc::new(df.schema().into());
+                let partitions = with_timeout(timeout, async {
+                    df.collect_partitioned().await.map_err(|err| anyhow!(err))
+                })
+                .await?;
+                // the rows are checked against the schema the plan produced them with
+                let schema = partitions
+                    .iter()
+                    .flatten()
+                    .next()
+                    .map(|batch| batch.schema())
+                    .unwrap_or(schema);
+                Arc::new(MemTable::try_new(schema, partitions)?)
+            }
+        })
+    }
+}
+
 // #[derive(Default)]
 pub struct DataSource {
     ctx: SessionContext,
     cached_schemas: DataSourceListing,
+    // how each added source was registered, so that it can be added again from a workspace
+    descriptors: BTreeMap<String, TableDescriptor>,
+    // files of sources with merged schemas that differ from the merged schema
+    schema_reports: BTreeMap<String, Vec<FileSchemaDiff>>,
+    // tables registered from query results rather than files
+    result_tables: BTreeMap<String, ResultTable>,
+    // SQL of views that can be saved in a workspace, in the order they were saved, as a view
+    // can read the ones before it
+    view_sql: Vec<(String, String)>,
+    query_timeout: Option<Duration>,
 }
 
+/// Written to workspaces, where fields left out take the same defaults as [`TableDescriptor::new`].
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TableDescriptor {
     url: Url,
     extension: Option<String>,
     account: Option<String>,
     table_name: Option<String>,
     load_metadata: bool,
+    profile: Option<String>,
+    region: Option<String>,
+    endpoint: Option<String>,
+    credentials: Option<String>,
+    format: TableFormat,
+    #[serde(with = "delimiter")]
+    delimiter: u8,
+    has_header: bool,
+    schema_infer_max_records: usize,
+    // detected from key=value directories when empty
+    partition_cols: Vec<(String, PartitionType)>,
+    // combine the schemas of files in a directory, rather than requiring them to match
+    merge_schemas: bool,
+}
+
+impl Default for TableDescriptor {
+    fn default() -> Self {
+        Self {
+            url: Url::parse("file:///").expect("Root path is a valid url"),
+            extension: None,
+            account: None,
+            table_name: None,
+            load_metadata: true,
+            profile: None,
+            region: None,
+            endpoint: None,
+            credentials: None,
+            format: TableFormat::Parquet,
+            delimiter: b',',
+            has_header: true,
+            schema_infer_max_records: 1000,
+            partition_cols: vec![],
+            merge_schemas: false,
+        }
+    }
+}
+
+/// Delimiters are written as a one character string, and read from either that or the byte
+/// value that earlier workspaces were written with.
+mod delimiter {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(delimiter: &u8, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&char::from(*delimiter).to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u8, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Delimiter {
+            Text(String),
+            Byte(u8),
+        }
+        match Delimiter::deserialize(deserializer)? {
+            Delimiter::Byte(byte) => Ok(byte),
+            Delimiter::Text(text) => match text.as_bytes() {
+                [byte] if byte.is_ascii() => Ok(*byte),
+                _ => Err(D::Error::custom(format!(
+                    "Delimiter must be a single ASCII character: {}",
+                    text
+                ))),
+            },
+        }
+    }
 }
 
 impl TableDescriptor {
@@ -57,17 +539,49 @@ impl TableDescriptor {
             .and_then(|s| s.to_str())
             .map(|s| s.to_string());
 
+        let format = ext
+            .as_deref()
+            .and_then(TableFormat::from_extension)
+            .unwrap_or(TableFormat::Parquet);
+        let delimiter = match ext.as_deref() {
+            Some("tsv") => b'\t',
+            _ => b',',
+        };
+
         Ok(Self {
             url: make_url_from_path(url)?,
             extension: ext,
-            account: None,
-            table_name: None,
-            load_metadata: true,
+            format,
+            delimiter,
+            ..Default::default()
         })
     }
 
     pub fn with_extension(mut self, extension: &str) -> Self {
         self.extension = Some(extension.to_owned());
+        if let Some(format) = TableFormat::from_extension(extension) {
+            self.format = format;
+        }
+        self
+    }
+
+    pub fn with_format(mut self, format: TableFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    pub fn with_header(mut self, has_header: bool) -> Self {
+        self.has_header = has_header;
+        self
+    }
+
+    pub fn with_schema_infer_max_records(mut self, max_records: usize) -> Self {
+        self.schema_infer_max_records = max_records;
         self
     }
 
@@ -85,6 +599,317 @@ impl TableDescriptor {
         self.table_name = Some(table_name.to_owned());
         self
     }
+
+    pub fn with_profile(mut self, profile: &str) -> Self {
+        self.profile = Some(profile.to_owned());
+        self
+    }
+
+    pub fn with_region(mut self, region: &str) -> Self {
+        self.region = Some(region.to_owned());
+        self
+    }
+
+    pub fn with_endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = Some(endpoint.to_owned());
+        self
+    }
+
+    pub fn with_credentials(mut self, credentials: &str) -> Self {
+        self.credentials = Some(credentials.to_owned());
+        self
+    }
+
+    pub fn with_merge_schemas(mut self, merge_schemas: bool) -> Self {
+        self.merge_schemas = merge_schemas;
+        self
+    }
+
+    pub fn with_partition_cols(mut self, partition_cols: Vec<(String, PartitionType)>) -> Self {
+        self.partition_cols = partition_cols;
+        self
+    }
+
+    /// Name the table will be registered as, defaults to the file name.
+    pub fn table_name(&self) -> String {
+        // TODO: get &str directly, rather than using String
+        match self.table_name {
+            Some(ref table_name) => table_name.clone(),
+            _ => Path::new(&self.url.path())
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .expect("Could not convert filename to default table name")
+                .to_lowercase(),
+        }
+    }
+
+    /// Read the files of the source into a table that can be registered with
+    /// [`DataSource::register_source`], along with how each file differs from the merged schema.
+    /// This lists the files and infers their schemas, so the data source shouldn't be locked.
+    pub async fn table(
+        &self,
+        state: &SessionState,
+    ) -> anyhow::Result<(Arc<dyn TableProvider>, Vec<FileSchemaDiff>)> {
+        if self.merge_schemas && !self.format.can_merge_schemas() {
+            return Err(anyhow!(
+                "Schemas can only be merged for Parquet and NDJSON sources, not {}",
+                self.format.name()
+            ));
+        }
+        self.add_object_store(state)?;
+
+        let extension = self
+            .extension
+            .as_deref()
+            .unwrap_or(self.format.default_extension());
+        let partition_cols = if self.partition_cols.is_empty() {
+            self.detect_partitions(state, extension)
+                .await?
+                .into_iter()
+                .map(|name| (name, DataType::Utf8))
+                .collect()
+        } else {
+            self.partition_cols
+                .iter()
+                .map(|(name, partition_type)| (name.clone(), partition_type.data_type()))
+                .collect()
+        };
+
+        let config = state.config();
+        let table_options = state.default_table_options();
+        let listing_options = match self.format {
+            TableFormat::Parquet => ParquetReadOptions {
+                file_extension: extension,
+                skip_metadata: Some(!self.load_metadata),
+                table_partition_cols: partition_cols,
+                ..Default::default()
+            }
+            .to_listing_options(config, table_options),
+            TableFormat::Csv => CsvReadOptions::new()
+                .file_extension(extension)
+                .delimiter(self.delimiter)
+                .has_header(self.has_header)
+                .schema_infer_max_records(self.schema_infer_max_records)
+                .table_partition_cols(partition_cols)
+                .to_listing_options(config, table_options),
+            TableFormat::Json => NdJsonReadOptions {
+                file_extension: extension,
+                schema_infer_max_records: self.schema_infer_max_records,
+                table_partition_cols: partition_cols,
+                ..Default::default()
+            }
+            .to_listing_options(config, table_options),
+            TableFormat::Avro => AvroReadOptions {
+                file_extension: extension,
+                table_partition_cols: partition_cols,
+                ..Default::default()
+            }
+            .to_listing_options(config, table_options),
+            TableFormat::Arrow => ArrowReadOptions {
+                file_extension: extension,
+                table_partition_cols: partition_cols,
+                ..Default::default()
+            }
+            .to_listing_options(config, table_options),
+        };
+
+        // without merging, the schema is inferred from the files and must be the same in each
+        let table_url = ListingTableUrl::parse(&self.url)?;
+        let (schema, schema_report) = if self.merge_schemas {
+            self.merge_file_schemas(state, &table_url, &listing_options)
+                .await?
+        } else {
+            (None, vec![])
+        };
+        let schema = match schema {
+            Some(schema) => schema,
+            None => listing_options.infer_schema(state, &table_url).await?,
+        };
+        let config = ListingTableConfig::new(table_url)
+            .with_listing_options(listing_options)
+            .with_schema(schema);
+        Ok((Arc::new(ListingTable::try_new(config)?), schema_report))
+    }
+
+    /// Infer the schema of each file of a directory source separately and merge them, along
+    /// with how each file differs from the merged schema.
+    async fn merge_file_schemas(
+        &self,
+        state: &SessionState,
+        table_url: &ListingTableUrl,
+        listing_options: &ListingOptions,
+    ) -> anyhow::Result<(Option<SchemaRef>, Vec<FileSchemaDiff>)> {
+        if !table_url.is_collection() {
+            return Ok((None, vec![]));
+        }
+        let store = state.runtime_env().object_store(table_url)?;
+        // partitioned sources read every nested file, others only those in the directory itself
+        let ignore_subdirectory = listing_options.table_partition_cols.is_empty()
+            && state
+                .config_options()
+                .execution
+                .listing_table_ignore_subdirectory;
+
+        let mut files = vec![];
+        let mut listing = store.list(Some(table_url.prefix()));
+        while let Some(meta) = listing.next().await {
+            let meta = meta?;
+            if meta
+                .location
+                .as_ref()
+                .ends_with(&listing_options.file_extension)
+                && table_url.contains(&meta.location, ignore_subdirectory)
+            {
+                files.push(meta);
+            }
+        }
+        if files.is_empty() {
+            return Ok((None, vec![]));
+        }
+        // in path order, so that columns added over time are placed after the older ones
+        files.sort_by(|a, b| a.location.cmp(&b.location));
+
+        let mut schemas = vec![];
+        for meta in files {
+            let file = meta.location.to_string();
+            let schema = listing_options
+                .format
+                .infer_schema(state, &store, &[meta])
+                .await?;
+            schemas.push((file, schema));
+        }
+        let (schema, report) = merge_schemas(&schemas)?;
+        Ok((Some(Arc::new(schema)), report))
+    }
+
+    /// Names of the key=value directories that files in a directory source are nested in.
+    /// Only a sample of files is checked, and every one must be nested in the same keys.
+    async fn detect_partitions(
+        &self,
+        state: &SessionState,
+        extension: &str,
+    ) -> anyhow::Result<Vec<String>> {
+        let table_url = ListingTableUrl::parse(&self.url)?;
+        if !table_url.is_collection() {
+            return Ok(vec![]);
+        }
+        let store = state.runtime_env().object_store(&table_url)?;
+        let prefix = table_url.prefix();
+
+        let mut keys: Option<Vec<String>> = None;
+        let mut listing = store
+            .list(Some(prefix))
+            .filter(|meta| {
+                meta.as_ref()
+                    .map_or(true, |meta| meta.location.as_ref().ends_with(extension))
+            })
+            .take(PARTITION_SAMPLE_FILES);
+        while let Some(meta) = listing.next().await {
+            let meta = meta?;
+            let Some(parts) = meta.location.prefix_match(prefix) else {
+                continue;
+            };
+            let mut parts = parts.collect_vec();
+            // the file itself
+            parts.pop();
+            let file_keys = parts
+                .iter()
+                .map(|part| part.as_ref().split_once('=').map(|(key, _)| key.to_owned()))
+                .collect::<Option<Vec<_>>>();
+            match (&keys, file_keys) {
+                (None, Some(file_keys)) => keys = Some(file_keys),
+                (Some(keys), Some(file_keys)) if *keys == file_keys => {}
+                _ => return Ok(vec![]),
+            }
+        }
+        Ok(keys.unwrap_or_default())
+    }
+
+    fn add_object_store(&self, state: &SessionState) -> anyhow::Result<()> {
+        match self.url.scheme() {
+            "wsl" | "wsllocalhost" => {
+                let prefix = format!(
+                    r"\\?\UNC\wsl.localhost\{}\",
+                    self.url.host().expect("WSL url must have host.")
+                );
+                let object_store = LocalFileSystem::new_with_prefix(prefix)?;
+                register_object_store(state, &self.url, object_store)?;
+            }
+            "az" | "azure" | "abfs" | "abfss" => {
+                let object_store = MicrosoftAzureBuilder::new()
+                    .with_url(self.url.to_string())
+                    .with_account(
+                        self.account
+                            .as_ref()
+                            .ok_or(anyhow!("Account required for Azure table"))?,
+                    )
+                    .with_use_azure_cli(true)
+                    .build()?;
+                dbg!("adding azure store");
+                register_object_store(state, &self.url, object_store)?;
+            }
+            "s3" | "s3a" => {
+                // credentials from the environment are the base, and are overridden by an
+                // explicitly chosen profile, which may set its own region and endpoint
+                let mut builder = AmazonS3Builder::from_env().with_url(self.url.to_string());
+                let settings = match (&self.profile, std::env::var("AWS_PROFILE")) {
+                    (Some(profile), _) => aws_profile_config(profile)?,
+                    // the environment profile may be resolved by other credential providers,
+                    // so one missing from the files falls back to the default chain
+                    (None, Ok(profile)) => aws_profile_config(&profile).unwrap_or_default(),
+                    (None, Err(_)) => vec![],
+                };
+                for (key, value) in settings {
+                    builder = builder.with_config(key, value);
+                }
+                if let Some(region) = self.region.as_ref() {
+                    builder = builder.with_region(region);
+                }
+                if let Some(endpoint) = self.endpoint.as_ref() {
+                    builder = builder.with_endpoint(endpoint);
+                }
+                // local S3-compatible stores (e.g. MinIO) are commonly served over http
+                let endpoint = builder.get_config_value(&AmazonS3ConfigKey::Endpoint);
+                if endpoint.is_some_and(|endpoint| endpoint.starts_with("http://")) {
+                    builder = builder.with_allow_http(true);
+                }
+                register_object_store(state, &self.url, builder.build()?)?;
+            }
+            "gs" => {
+                // without an explicit credentials file, application default credentials are used
+                let mut builder =
+                    GoogleCloudStorageBuilder::from_env().with_url(self.url.to_string());
+                if let Some(credentials) = self.credentials.as_ref() {
+                    builder = builder
+                        .with_application_credentials(shellexpand::full(credentials)?.into_owned());
+                }
+                register_object_store(state, &self.url, builder.build()?)?;
+            }
+            "http" | "https" => {
+                // paths are resolved against the store root, so only give it the host
+                let object_store = HttpBuilder::new()
+                    .with_url(&self.url[url::Position::BeforeScheme..url::Position::AfterPort])
+                    .with_config(ClientConfigKey::AllowHttp, "true")
+                    .build()?;
+                register_object_store(state, &self.url, object_store)?;
+            }
+            _ => {}
+        };
+
+        Ok(())
+    }
+}
+
+fn register_object_store(
+    state: &SessionState,
+    url: &Url,
+    object_store: impl ObjectStore,
+) -> anyhow::Result<()> {
+    state.runtime_env().register_object_store(
+        &Url::parse(&url[url::Position::BeforeScheme..url::Position::AfterPort])?,
+        Arc::new(object_store),
+    );
+    Ok(())
 }
 
 impl Default for DataSource {
@@ -102,32 +927,136 @@ impl Default for DataSource {
         Self {
             ctx: SessionContext::new_with_config(config),
             cached_schemas: BTreeMap::new(),
+            descriptors: BTreeMap::new(),
+            schema_reports: BTreeMap::new(),
+            result_tables: BTreeMap::new(),
+            view_sql: vec![],
+            query_timeout: None,
         }
     }
 }
 
-#[derive(Clone)]
+/// A query result, fetched lazily in pages of [`PAGE_SIZE`] rows as they're needed for display.
 pub struct Data {
-    // TOOD: arc context into this struct?
-    pub data: RecordBatch,
-    pub sort_state: Option<(String, SortState)>,
+    // query as originally submitted, so that sorts and filters replace each other rather than stacking.
+    // frames carry the whole session state, so are boxed to keep Data small
+    source: Box<DataFrame>,
+    df: Box<DataFrame>,
+    schema: SchemaRef,
+    // pages of the current run of the query, read in order by the reader
+    pages: BTreeMap<usize, RecordBatch>,
+    reader: Option<PageReader>,
+    pending_page: Option<Task<anyhow::Result<(PageReader, RecordBatch)>>>,
+    // page the current run reads next, and the furthest page the table needs
+    next_page: usize,
+    wanted_page: usize,
+    // a page read on its own rather than from the run, and its number
+    pending_seek: Option<(usize, Task<anyhow::Result<RecordBatch>>)>,
+    // set when reading a page fails or is cancelled, so that it isn't started again every frame
+    reading_stopped: bool,
+    first_page_read: Option<Instant>,
+    row_count: Option<usize>,
+    row_count_task: Option<Task<anyhow::Result<usize>>>,
+    pub sort: Vec<SortKey>,
+    pub filters: Vec<Filter>,
+    // distinct values of a column, or None if there are too many to list
+    distinct_values: BTreeMap<String, Option<Vec<String>>>,
+    pending_distinct_values: BTreeMap<String, Task<anyhow::Result<Option<Vec<String>>>>>,
+    // applied to each query run for this data
+    timeout: Option<Duration>,
+    // set when the data is the difference between two tables
+    comparison: Option<Arc<Comparison>>,
+    // SQL of the source query, for saving the data as a view. None once columns are expanded or
+    // exploded, which aren't written as SQL
+    sql: Option<String>,
+    pub columns: ColumnLayout,
+    pub selection: Option<Selection>,
+    copy_task: Option<Task<anyhow::Result<String>>>,
 }
 
-fn get_read_options(table: &TableDescriptor) -> ParquetReadOptions<'_> {
-    // TODO: use this to decide the format to load the file in, with user configurable extensions
-    match table.extension.as_ref() {
-        Some(ext) => ParquetReadOptions {
-            file_extension: ext,
-            skip_metadata: Some(!table.load_metadata),
-            ..Default::default()
-        },
-        _ => ParquetReadOptions {
-            skip_metadata: Some(!table.load_metadata),
-            ..Default::default()
-        },
+/// A run of a query, read in order a page at a time. Queries that don't sort their rows, such as
+/// those reading several files at once, may return them in a different order each time they're
+/// run, so pages are read from the same run where possible. Pages behind the run, or far ahead of
+/// it, are read on their own by [`Data::read_page_at`], and for those queries may repeat or miss
+/// rows of the pages around them.
+struct PageReader {
+    stream: SendableRecordBatchStream,
+    // rows read past the end of the last page
+    rest: Vec<RecordBatch>,
+    // when the last page was read, which times the query by its first page
+    read_at: Instant,
+}
+
+impl PageReader {
+    async fn start(df: DataFrame) -> anyhow::Result<Self> {
+        Ok(Self {
+            stream: df.execute_stream().await?,
+            rest: vec![],
+            read_at: Instant::now(),
+        })
+    }
+
+    /// Read the next [`PAGE_SIZE`] rows, fewer only at the end of the rows.
+    async fn read_page(mut self) -> anyhow::Result<(Self, RecordBatch)> {
+        let mut rows = self.rest.iter().map(RecordBatch::num_rows).sum::<usize>();
+        while rows < PAGE_SIZE {
+            match self.stream.next().await {
+                Some(batch) => {
+                    let batch = batch?;
+                    rows += batch.num_rows();
+                    self.rest.push(batch);
+                }
+                None => break,
+            }
+        }
+        let batch = concat_record_batches(&self.stream.schema(), mem::take(&mut self.rest))?;
+        if rows > PAGE_SIZE {
+            self.rest.push(batch.slice(PAGE_SIZE, rows - PAGE_SIZE));
+        }
+        self.read_at = Instant::now();
+        Ok((self, batch.slice(0, rows.min(PAGE_SIZE))))
     }
 }
 
+fn as_parquet_listing(table: &dyn TableProvider) -> Option<&ListingTable> {
+    table
+        .as_any()
+        .downcast_ref::<ListingTable>()
+        .filter(|listing| listing.options().format.as_any().is::<ParquetFormat>())
+}
+
+pub fn is_parquet(table: &dyn TableProvider) -> bool {
+    as_parquet_listing(table).is_some()
+}
+
+/// Read the footer of every file of a parquet source. This fetches each file, so `state` is
+/// taken from the data source beforehand rather than holding it.
+pub async fn parquet_metadata(
+    table: &dyn TableProvider,
+    state: &SessionState,
+) -> anyhow::Result<ParquetFiles> {
+    let listing = as_parquet_listing(table).ok_or(anyhow!("Not a parquet source"))?;
+
+    let mut files = vec![];
+    for url in listing.table_paths() {
+        let store = state.runtime_env().object_store(url)?;
+        let mut listed = url
+            .list_all_files(state, store.as_ref(), &listing.options().file_extension)
+            .await?;
+        while let Some(meta) = listed.next().await {
+            let meta = meta?;
+            let location = meta.location.to_string();
+            let metadata = ParquetObjectReader::new(store.clone(), meta)
+                .with_preload_column_index(true)
+                .with_preload_offset_index(true)
+                .get_metadata()
+                .await?;
+            files.push((location, metadata));
+        }
+    }
+    Ok(files)
+}
+
 fn filesystem_path_to_url(path: &Path) -> anyhow::Result<Url> {
     if path.is_file() {
         Url::from_file_path(path)
@@ -181,15 +1110,121 @@ fn make_url_from_path(path: &str) -> anyhow::Result<Url> {
     Ok(dbg!(url))
 }
 
-fn concat_record_batches(batches: Vec<RecordBatch>) -> anyhow::Result<RecordBatch> {
-    concat_batches(
-        &batches.first().ok_or(anyhow!("Data is empty."))?.schema(),
-        batches.iter(),
-    )
-    .map_err(|err| anyhow!(err))
+/// Read the settings for a named profile from the shared AWS credentials and config files,
+/// keeping only the keys that the S3 object store understands.
+fn aws_profile_config(profile: &str) -> anyhow::Result<Vec<(AmazonS3ConfigKey, String)>> {
+    let credentials_file = std::env::var("AWS_SHARED_CREDENTIALS_FILE")
+        .unwrap_or_else(|_| "~/.aws/credentials".to_owned());
+    let config_file =
+        std::env::var("AWS_CONFIG_FILE").unwrap_or_else(|_| "~/.aws/config".to_owned());
+    // the config file prefixes every section other than default with "profile"
+    let config_section = match profile {
+        "default" => profile.to_owned(),
+        _ => format!("profile {}", profile),
+    };
+
+    let mut found = false;
+    let mut settings = vec![];
+    for (file, section) in [
+        (credentials_file, profile.to_owned()),
+        (config_file, config_section),
+    ] {
+        let Ok(contents) = std::fs::read_to_string(shellexpand::tilde(&file).as_ref()) else {
+            continue;
+        };
+
+        let mut in_section = false;
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                in_section = name.trim() == section;
+                found |= in_section;
+            } else if let (true, Some((key, value))) = (in_section, line.split_once('=')) {
+                // unknown keys (output format, nested service sections, ...) are skipped
+                if let Ok(key) = key.trim().to_ascii_lowercase().parse() {
+                    settings.push((key, value.trim().to_owned()));
+                }
+            }
+        }
+    }
+
+    if found {
+        Ok(settings)
+    } else {
+        Err(anyhow!("Could not find AWS profile {}", profile))
+    }
+}
+
+/// Quotes identifiers that would otherwise be normalized to lower case by DataFusion.
+pub fn quote_identifier(name: &str) -> String {
+    let is_plain = name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        && !name.starts_with(|c: char| c.is_ascii_digit());
+    if is_plain {
+        name.to_owned()
+    } else {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    }
+}
+
+fn concat_record_batches(
+    schema: &SchemaRef,
+    batches: Vec<RecordBatch>,
+) -> anyhow::Result<RecordBatch> {
+    // prefer the schema of the data itself, which may differ from the plan in nullability
+    let schema = batches
+        .first()
+        .map(|batch| batch.schema())
+        .unwrap_or(schema.clone());
+    concat_batches(&schema, batches.iter()).map_err(|err| anyhow!(err))
 }
 
 impl DataSource {
+    /// Tables found by the last call to [`DataSource::list_tables`].
+    pub fn tables(&self) -> &DataSourceListing {
+        &self.cached_schemas
+    }
+
+    /// Files of a source that differ from its merged schema, if its schemas were merged.
+    pub fn schema_report(&self, table_name: &str) -> Option<&[FileSchemaDiff]> {
+        self.schema_reports.get(table_name).map(Vec::as_slice)
+    }
+
+    /// How a table saved from a query result is read, None for tables of files.
+    pub fn result_table(&self, table_name: &str) -> Option<ResultTable> {
+        self.result_tables.get(table_name).copied()
+    }
+
+    /// Register `table`, made from a query result with [`ResultTable::table`], replacing any
+    /// table with the same name. Views with the `sql` they were made from are saved in
+    /// workspaces.
+    pub fn register_result(
+        &mut self,
+        table_name: &str,
+        table: Arc<dyn TableProvider>,
+        kind: ResultTable,
+        sql: Option<String>,
+    ) -> anyhow::Result<()> {
+        let table_name = table_name.to_lowercase();
+        if self.ctx.table_exist(&table_name)? {
+            self.delete_data_source(&table_name)?;
+        }
+        self.ctx.register_table(&table_name, table)?;
+        if let (ResultTable::View, Some(sql)) = (kind, sql) {
+            self.view_sql.push((table_name.clone(), sql));
+        }
+        self.result_tables.insert(table_name, kind);
+        Ok(())
+    }
+
+    /// Names and SQL of the views that can be saved in a workspace, each after those it may read.
+    pub fn view_sql(&self) -> &[(String, String)] {
+        &self.view_sql
+    }
+
     pub async fn list_tables(&mut self) -> &DataSourceListing {
         // TODO: is there anything to be done to simplify this arrow?
         for catalog_name in self.ctx.catalog_names() {
@@ -217,52 +1252,32 @@ impl DataSource {
         &self.cached_schemas
     }
 
-    fn add_object_store_for_table(&mut self, table: &TableDescriptor) -> anyhow::Result<()> {
-        match table.url.scheme() {
-            "wsl" | "wsllocalhost" => {
-                let prefix = format!(
-                    r"\\?\UNC\wsl.localhost\{}\",
-                    table.url.host().expect("WSL url must have host.")
-                );
-                let object_store = LocalFileSystem::new_with_prefix(prefix)?;
-                self.ctx.register_object_store(
-                    &dbg!(Url::parse(
-                        &table.url[url::Position::BeforeScheme..url::Position::AfterHost]
-                    ))?,
-                    Arc::new(object_store),
-                );
-            }
-            "az" | "azure" | "abfs" | "abfss" => {
-                let object_store = MicrosoftAzureBuilder::new()
-                    .with_url(table.url.to_string())
-                    .with_account(
-                        table
-                            .account
-                            .as_ref()
-                            .ok_or(anyhow!("Account required for Azure table"))?,
-                    )
-                    .with_use_azure_cli(true)
-                    .build()?;
-                dbg!("adding azure store");
-                self.ctx.register_object_store(
-                    &dbg!(Url::parse(
-                        &table.url[url::Position::BeforeScheme..url::Position::AfterHost]
-                    ))?,
-                    Arc::new(object_store),
-                );
-            }
-            _ => {}
-        };
-
-        Ok(())
-    }
-
     pub fn rename_data_source(
         &mut self,
         from_name: &str,
         to_name: &str,
     ) -> anyhow::Result<Arc<dyn TableProvider>> {
+        let descriptor = self.descriptors.remove(from_name);
+        let schema_report = self.schema_reports.remove(from_name);
+        let result_table = self.result_tables.remove(from_name);
+        // renamed in place, so that views stay after those they read
+        for (name, _) in self.view_sql.iter_mut() {
+            if name == from_name {
+                *name = to_name.to_owned();
+            }
+        }
         let table = self.delete_data_source(from_name)?;
+        if let Some(descriptor) = descriptor {
+            self.descriptors
+                .insert(to_name.to_owned(), descriptor.with_table_name(to_name));
+        }
+        if let Some(schema_report) = schema_report {
+            self.schema_reports
+                .insert(to_name.to_owned(), schema_report);
+        }
+        if let Some(result_table) = result_table {
+            self.result_tables.insert(to_name.to_owned(), result_table);
+        }
         // will be added back to cache when accessed, don't need to add now
         self.ctx
             .register_table(to_name, table)
@@ -273,82 +1288,887 @@ impl DataSource {
     pub fn delete_data_source(&mut self, source: &str) -> anyhow::Result<Arc<dyn TableProvider>> {
         if let Some(table) = self.ctx.deregister_table(source)? {
             self.cached_schemas.remove(source);
+            self.descriptors.remove(source);
+            self.schema_reports.remove(source);
+            self.result_tables.remove(source);
+            self.view_sql.retain(|(name, _)| name != source);
             Ok(table)
         } else {
             Err(anyhow!("Error retrieving table"))
         }
     }
 
-    pub async fn add_data_source(&mut self, source: TableDescriptor) -> anyhow::Result<String> {
-        self.add_object_store_for_table(&source)?;
-
-        // TODO: get &str directly, rather than using String
-        let table_name = match source.table_name {
-            Some(ref table_name) => table_name.clone(),
-            _ => Path::new(&source.url.path())
-                .file_stem()
-                .and_then(|s| s.to_str())
-                .expect("Could not convert filename to default table name")
-                .to_lowercase(),
-        };
-
+    /// Register `table`, read from `source` with [`TableDescriptor::table`], replacing any table
+    /// with the same name.
+    pub fn register_source(
+        &mut self,
+        source: TableDescriptor,
+        table: Arc<dyn TableProvider>,
+        schema_report: Vec<FileSchemaDiff>,
+    ) -> anyhow::Result<String> {
+        let table_name = source.table_name();
         if self.ctx.table_exist(&table_name)? {
             self.delete_data_source(&table_name)?;
         }
+        self.ctx.register_table(&table_name, table)?;
 
-        let read_options = get_read_options(&source);
+        if !schema_report.is_empty() {
+            self.schema_reports
+                .insert(table_name.clone(), schema_report);
+        }
+        self.descriptors.insert(table_name.clone(), source);
+        Ok(table_name)
+    }
 
-        // TODO: register listing table rather than
+    pub async fn add_data_source(&mut self, source: TableDescriptor) -> anyhow::Result<String> {
+        let (table, schema_report) = source.table(&self.ctx.state()).await?;
+        self.register_source(source, table, schema_report)
+    }
 
-        self.ctx
-            .register_parquet(&table_name, source.url.as_ref(), read_options)
-            .await?;
+    /// A copy of the session, for reading sources without holding the data source.
+    pub fn state(&self) -> SessionState {
+        self.ctx.state()
+    }
 
-        Ok(table_name.to_owned())
+    /// Sources added with [`DataSource::add_data_source`], under their current names.
+    pub fn table_descriptors(&self) -> Vec<TableDescriptor> {
+        self.descriptors.values().cloned().collect()
     }
 
-    pub async fn query(&self, query: Query) -> anyhow::Result<Data> {
-        let df = match &query {
+    pub async fn table_provider(&self, table_name: &str) -> anyhow::Result<Arc<dyn TableProvider>> {
+        Ok(self.ctx.table_provider(table_name).await?)
+    }
+
+    /// Names of every function available to SQL queries, sorted and deduplicated.
+    pub fn function_names(&self) -> Vec<String> {
+        let state = self.ctx.state();
+        state
+            .scalar_functions()
+            .keys()
+            .chain(state.aggregate_functions().keys())
+            .chain(state.window_functions().keys())
+            .cloned()
+            // the remaining built in functions aren't registered as udfs yet
+            .chain(AggregateFunction::iter().map(|function| function.to_string()))
+            .chain(BuiltInWindowFunction::iter().map(|function| function.to_string()))
+            .map(|name| name.to_lowercase())
+            .sorted()
+            .dedup()
+            .collect()
+    }
+
+    pub async fn dataframe(&self, query: Query) -> anyhow::Result<DataFrame> {
+        Ok(match &query {
             Query::TableName(table) => self.ctx.table(table.to_lowercase()).await?,
             Query::Sql(query) => self.ctx.sql(query).await?,
-        };
+        })
+    }
 
-        let data = df.collect().await?;
+    pub async fn query(&self, query: Query) -> anyhow::Result<Data> {
+        let df = with_timeout(self.query_timeout, self.dataframe(query.clone())).await?;
+        Ok(Data::new(df, self.query_timeout).with_sql(Some(query.sql())))
+    }
 
-        Ok(Data {
-            // TODO: will record batches have the same schema, or should these really be
-            // TODO: separate data entries?
-            data: concat_record_batches(data)?,
-            sort_state: None,
-        })
+    /// Limit on how long each query may run for, if there is one.
+    pub fn query_timeout(&self) -> Option<Duration> {
+        self.query_timeout
+    }
+
+    /// Limit on how long each query may run for, None to let queries run until they finish.
+    pub fn set_query_timeout(&mut self, timeout: Option<Duration>) {
+        self.query_timeout = timeout;
+    }
+}
+
+/// Fail with a timeout error if `future` hasn't finished within `timeout`.
+pub async fn with_timeout<T>(
+    timeout: Option<Duration>,
+    future: impl Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    match timeout {
+        Some(timeout) => {
+            future
+                .or(async move {
+                    Timer::after(timeout).await;
+                    Err(anyhow!(
+                        "Query timed out after {}s, the timeout can be changed in the settings",
+                        timeout.as_secs()
+                    ))
+                })
+                .await
+        }
+        None => future.await,
     }
 }
 
 impl Data {
-    pub async fn sort(self, col: String, sort: SortState) -> anyhow::Result<Self> {
-        // TODO: should this be a clone of the exising context?
-        // TODO: make successive queries able to be registered to the context, so that comple
-        // TODO: queries can be constructed?
-        let ctx = SessionContext::new();
-        let mut df = ctx.read_batch(self.data)?;
-        df = match &sort {
-            // consider null "less" than real values, so they can get surfaced
-            SortState::Ascending => df.sort(vec![col_expr(&col).sort(true, false)])?,
-            SortState::Descending => df.sort(vec![col_expr(&col).sort(false, true)])?,
-            _ => df,
+    pub fn new(df: DataFrame, timeout: Option<Duration>) -> Self {
+        Self::with_query(df.clone(), df, vec![], vec![], timeout)
+    }
+
+    fn with_query(
+        source: DataFrame,
+        df: DataFrame,
+        filters: Vec<Filter>,
+        sort: Vec<SortKey>,
+        timeout: Option<Duration>,
+    ) -> Self {
+        let schema: SchemaRef = Arc::new(df.schema().into());
+        let count = df.clone();
+        Data {
+            source: Box::new(source),
+            df: Box::new(df),
+            schema,
+            pages: BTreeMap::new(),
+            reader: None,
+            pending_page: None,
+            pending_seek: None,
+            next_page: 0,
+            wanted_page: 0,
+            reading_stopped: false,
+            first_page_read: None,
+            row_count: None,
+            row_count_task: Some(smol::spawn(Compat::new(with_timeout(
+                timeout,
+                async move { count.count().await.map_err(|err| anyhow!(err)) },
+            )))),
+            sort,
+            filters,
+            distinct_values: BTreeMap::new(),
+            pending_distinct_values: BTreeMap::new(),
+            timeout,
+            comparison: None,
+            sql: None,
+            columns: ColumnLayout::default(),
+            selection: None,
+            copy_task: None,
+        }
+    }
+
+    /// Run a comparison of two tables, `df` being the plan of its query, counting the rows that
+    /// differ in each way.
+    pub async fn compare(
+        df: DataFrame,
+        mut comparison: Comparison,
+        timeout: Option<Duration>,
+    ) -> DataResult {
+        with_timeout(timeout, comparison.count_rows(df.clone())).await?;
+        let sql = comparison.sql();
+        Ok(Data::new(df, timeout)
+            .with_comparison(Some(Arc::new(comparison)))
+            .with_sql(Some(sql)))
+    }
+
+    pub fn with_comparison(mut self, comparison: Option<Arc<Comparison>>) -> Self {
+        self.comparison = comparison;
+        self
+    }
+
+    pub fn with_sql(mut self, sql: Option<String>) -> Self {
+        self.sql = sql;
+        self
+    }
+
+    pub fn with_columns(mut self, columns: ColumnLayout) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// The tables this data compares, if it is a comparison.
+    pub fn comparison(&self) -> Option<&Comparison> {
+        self.comparison.as_deref()
+    }
+
+    /// The data as one SQL query, with its filters and sort, if it can be written as one.
+    pub fn view_sql(&self) -> Option<String> {
+        let mut sql = format!("SELECT * FROM ({})", self.sql.as_ref()?);
+        if !self.filters.is_empty() {
+            let conditions = self
+                .filters
+                .iter()
+                .map(Filter::to_sql)
+                .collect::<anyhow::Result<Vec<_>>>()
+                .ok()?;
+            sql += &format!(" WHERE {}", conditions.join(" AND "));
+        }
+        let sort = self.sort.iter().filter_map(SortKey::to_sql).collect_vec();
+        if !sort.is_empty() {
+            sql += &format!(" ORDER BY {}", sort.join(", "));
+        }
+        Some(sql)
+    }
+
+    /// Apply filters then a sort to the source query.
+    fn build_query(
+        source: &DataFrame,
+        filters: &[Filter],
+        sort: &[SortKey],
+    ) -> anyhow::Result<DataFrame> {
+        let mut df = source.clone();
+        if let Some(predicate) = filters
+            .iter()
+            .map(Filter::to_expr)
+            .reduce(|left, right| Ok(left?.and(right?)))
+        {
+            df = df.filter(predicate?)?;
+        }
+        let sort_exprs = sort.iter().filter_map(SortKey::to_expr).collect_vec();
+        if !sort_exprs.is_empty() {
+            df = df.sort(sort_exprs)?;
+        }
+        Ok(df)
+    }
+
+    /// Replace the sort on the data, keeping the current filters. Earlier keys take priority.
+    pub async fn sort(self, sort: Vec<SortKey>) -> anyhow::Result<Self> {
+        // sorts are applied to the query rather than fetched data, so each page is sorted
+        // relative to the whole result, and only the rows of the visible pages are kept
+        let source = *self.source;
+        let df = Self::build_query(&source, &self.filters, &sort)?;
+        Ok(
+            Data::with_query(source, df, self.filters, sort, self.timeout)
+                .with_comparison(self.comparison)
+                .with_sql(self.sql)
+                .with_columns(self.columns),
+        )
+    }
+
+    /// Replace the filters on the data, keeping the current sort.
+    pub async fn filter(self, filters: Vec<Filter>) -> anyhow::Result<Self> {
+        let source = *self.source;
+        let df = Self::build_query(&source, &filters, &self.sort)?;
+        Ok(
+            Data::with_query(source, df, filters, self.sort, self.timeout)
+                .with_comparison(self.comparison)
+                .with_sql(self.sql)
+                .with_columns(self.columns),
+        )
+    }
+
+    /// Replace the source with `source`, keeping the filters and sort keys whose columns remain.
+    fn with_source(self, source: DataFrame) -> anyhow::Result<Self> {
+        let schema = source.schema();
+        let has_column = |column: &str| schema.field_with_unqualified_name(column).is_ok();
+        let filters = self
+            .filters
+            .into_iter()
+            .filter(|filter| has_column(&filter.column))
+            .collect_vec();
+        let sort = self
+            .sort
+            .into_iter()
+            .filter(|key| has_column(&key.column))
+            .collect_vec();
+        let df = Self::build_query(&source, &filters, &sort)?;
+        Ok(Data::with_query(source, df, filters, sort, self.timeout)
+            .with_comparison(self.comparison)
+            .with_columns(self.columns))
+    }
+
+    /// Replace a struct column with a column for each of its fields, named `column.field`.
+    pub async fn expand_struct(self, col: String) -> anyhow::Result<Self> {
+        let source = (*self.source).clone();
+        let mut columns = vec![];
+        for (qualifier, field) in source.schema().iter() {
+            let column = Expr::Column(Column::new(qualifier.cloned(), field.name()));
+            match field.data_type() {
+                DataType::Struct(fields) if *field.name() == col => {
+                    columns.extend(fields.iter().map(|child| {
+                        get_field(column.clone(), child.name().as_str()).alias(format!(
+                            "{}.{}",
+                            col,
+                            child.name()
+                        ))
+                    }));
+                }
+                _ => columns.push(column),
+            }
+        }
+        self.with_source(source.select(columns)?)
+    }
+
+    /// Replace a list column with a row for each of its elements.
+    pub async fn explode_list(self, col: String) -> anyhow::Result<Self> {
+        let source = (*self.source).clone().unnest_columns(&[&col])?;
+        self.with_source(source)
+    }
+
+    /// Distinct values of a column in the unfiltered data, for picking filter values. Returns
+    /// None while they're fetched, and Some(None) if there are too many to list.
+    pub fn distinct_values(&mut self, col: &str) -> Option<Option<&[String]>> {
+        if let Some(values) = self.distinct_values.get(col) {
+            return Some(values.as_deref());
+        }
+        self.pending_distinct_values
+            .entry(col.to_owned())
+            .or_insert_with(|| {
+                let source = self.source.clone();
+                let col = col.to_owned();
+                smol::spawn(Compat::new(with_timeout(self.timeout, async move {
+                    let batches = source
+                        .aggregate(vec![ident(&col)], vec![])?
+                        .filter(ident(&col).is_not_null())?
+                        .sort(vec![ident(&col).sort(true, false)])?
+                        .limit(0, Some(MAX_PICK_VALUES + 1))?
+                        .collect()
+                        .await?;
+                    let mut values = vec![];
+                    for batch in batches {
+                        for row in 0..batch.num_rows() {
+                            values.push(array_value_to_string(batch.column(0), row)?);
+                        }
+                    }
+                    Ok((values.len() <= MAX_PICK_VALUES).then_some(values))
+                })))
+            });
+        None
+    }
+
+    pub fn schema(&self) -> Arc<Schema> {
+        self.schema.clone()
+    }
+
+    /// The query for this data, including any applied sort.
+    pub fn dataframe(&self) -> DataFrame {
+        (*self.df).clone()
+    }
+
+    /// The query for this data with its filters but not its sort, for aggregating over.
+    pub fn unsorted_dataframe(&self) -> anyhow::Result<DataFrame> {
+        Self::build_query(&self.source, &self.filters, &[])
+    }
+
+    /// Tables read by the query, before any sort or filter.
+    pub fn table_names(&self) -> Vec<String> {
+        let mut tables = BTreeSet::new();
+        // the closure never fails, so neither can the traversal
+        let _ = self
+            .source
+            .logical_plan()
+            .apply_with_subqueries(&mut |node: &LogicalPlan| {
+                if let LogicalPlan::TableScan(scan) = node {
+                    tables.insert(scan.table_name.table().to_owned());
+                }
+                Ok(TreeNodeRecursion::Continue)
+            });
+        tables.into_iter().collect()
+    }
+
+    /// Start copying the selected cells as `format`, the text is returned by
+    /// [`Data::take_copied`] once it's ready. Selections refer to columns by their position in the
+    /// table, `displayed` holds the schema index of the column at each position.
+    pub fn copy_selection(&mut self, format: CopyFormat, displayed: &[usize]) {
+        let Some(selection) = self.selection else {
+            return;
         };
+        let columns = selection
+            .columns(displayed.len())
+            .filter_map(|position| displayed.get(position).copied())
+            .collect_vec();
+        let schema = match self.schema.project(&columns) {
+            Ok(schema) => Arc::new(schema),
+            Err(err) => {
+                self.copy_task = Some(smol::spawn(async move { Err(anyhow!(err)) }));
+                return;
+            }
+        };
+        let select = schema
+            .fields()
+            .iter()
+            .map(|field| ident(field.name()))
+            .collect_vec();
+        let (first_row, rows) = selection.rows();
+        // rows that have been read are copied as they're shown, as running the query again may
+        // return them in another order
+        if let Some(batches) = rows
+            .or(self.row_count)
+            .filter(|&rows| rows <= MAX_COPY_ROWS)
+            .and_then(|rows| self.cached_rows(first_row, rows, &columns))
+        {
+            self.copy_task = Some(smol::spawn(async move {
+                format_selection(schema, &batches, format, selection.is_cell())
+            }));
+            return;
+        }
+        // one more row than can be copied, to tell whether there were too many
+        let limit = rows.unwrap_or(MAX_COPY_ROWS + 1).min(MAX_COPY_ROWS + 1);
+        let df = self.dataframe();
+        self.copy_task = Some(smol::spawn(Compat::new(with_timeout(
+            self.timeout,
+            async move {
+                let batches = df
+                    .limit(first_row, Some(limit))?
+                    .select(select)?
+                    .collect()
+                    .await?;
+                if batches.iter().map(RecordBatch::num_rows).sum::<usize>() > MAX_COPY_ROWS {
+                    return Err(anyhow!(
+                        "Can't copy more than {} rows, export them instead",
+                        MAX_COPY_ROWS
+                    ));
+                }
+                format_selection(schema, &batches, format, selection.is_cell())
+            },
+        ))));
+    }
+
+    /// The `columns` of `rows` rows from `first`, if every page they're on has been read. Rows
+    /// past the end of the data are left out.
+    fn cached_rows(
+        &self,
+        first: usize,
+        rows: usize,
+        columns: &[usize],
+    ) -> Option<Vec<RecordBatch>> {
+        let end = first + rows;
+        (first / PAGE_SIZE..end.div_ceil(PAGE_SIZE))
+            .map(|page| {
+                let batch = self.pages.get(&page)?;
+                let start = first.saturating_sub(page * PAGE_SIZE);
+                let stop = (end - page * PAGE_SIZE).min(PAGE_SIZE);
+                if stop > batch.num_rows() && self.row_count.is_none() {
+                    return None;
+                }
+                let stop = stop.min(batch.num_rows());
+                let batch = batch.project(columns).ok()?;
+                Some(batch.slice(start.min(stop), stop.saturating_sub(start)))
+            })
+            .collect()
+    }
+
+    /// Text of a finished copy, to put on the clipboard.
+    pub fn take_copied(&mut self) -> Option<anyhow::Result<String>> {
+        if self
+            .copy_task
+            .as_ref()
+            .is_some_and(|task| task.is_finished())
+        {
+            let task = self.copy_task.take().expect("Checked above");
+            Some(smol::block_on(task))
+        } else {
+            None
+        }
+    }
+
+    /// Whether the rows are still being counted.
+    pub fn is_counting(&self) -> bool {
+        self.row_count_task.is_some()
+    }
+
+    /// Stop counting the rows, which leaves the count unknown.
+    pub fn cancel_count(&mut self) {
+        self.row_count_task = None;
+    }
 
-        let data = df.collect().await?;
+    /// When the first page of rows was read, if it has been.
+    pub fn first_page_read(&self) -> Option<Instant> {
+        self.first_page_read
+    }
+
+    /// Whether reading rows failed or was cancelled, so rows not yet read are left blank.
+    pub fn is_reading_stopped(&self) -> bool {
+        self.reading_stopped
+    }
+
+    /// Whether rows are being read or counted.
+    pub fn is_running(&self) -> bool {
+        self.pending_page.is_some() || self.pending_seek.is_some() || self.row_count_task.is_some()
+    }
+
+    /// Stop reading and counting rows, which stops running the query. Rows that haven't been
+    /// read are left blank.
+    pub fn cancel(&mut self) {
+        self.pending_page = None;
+        self.pending_seek = None;
+        self.reader = None;
+        self.reading_stopped = true;
+        self.cancel_count();
+    }
 
-        Ok(Data {
-            // TODO: will record batches have the same schema, or should these really be
-            // TODO: separate data entries?
-            data: concat_record_batches(data)?,
-            sort_state: Some((col, sort)),
+    /// Total rows in the result, if they've been counted.
+    pub fn row_count(&self) -> Option<usize> {
+        self.row_count
+    }
+
+    /// Rows to lay out for display. Until the count is known, this extends one page past
+    /// the furthest page fetched so the table can continue to be scrolled.
+    pub fn num_rows(&self) -> usize {
+        self.row_count.unwrap_or_else(|| {
+            // the run may have started again, so the table keeps the rows it was scrolled to
+            let pages =
+                (self.next_page + self.pending_page.is_some() as usize).max(self.wanted_page + 1);
+            (pages + 1) * PAGE_SIZE
         })
     }
 
-    pub fn schema(&self) -> Arc<Schema> {
-        self.data.schema()
+    /// Resolve finished page and row count fetches. Returns true if any are still pending.
+    pub fn poll(&mut self) -> anyhow::Result<bool> {
+        let mut result = Ok(());
+
+        if self
+            .row_count_task
+            .as_ref()
+            .is_some_and(|task| task.is_finished())
+        {
+            let task = self.row_count_task.take().expect("Checked above");
+            match smol::block_on(task) {
+                Ok(count) => self.row_count = Some(count),
+                Err(err) => result = Err(err),
+            }
+        }
+
+        if self
+            .pending_page
+            .as_ref()
+            .is_some_and(|task| task.is_finished())
+        {
+            let task = self.pending_page.take().expect("Checked above");
+            let page = self.next_page;
+            match smol::block_on(task) {
+                Ok((reader, batch)) => {
+                    if page == 0 {
+                        self.first_page_read.get_or_insert(reader.read_at);
+                    }
+                    self.next_page += 1;
+                    if batch.num_rows() < PAGE_SIZE {
+                        // a short page is the end of the data
+                        self.row_count
+                            .get_or_insert(page * PAGE_SIZE + batch.num_rows());
+                    } else {
+                        self.reader = Some(reader);
+                    }
+                    self.pages.insert(page, batch);
+                    self.read_next_page();
+                }
+                Err(err) => {
+                    self.reading_stopped = true;
+                    result = Err(err);
+                }
+            }
+        }
+
+        if self
+            .pending_seek
+            .as_ref()
+            .is_some_and(|(_, task)| task.is_finished())
+        {
+            let (page, task) = self.pending_seek.take().expect("Checked above");
+            match smol::block_on(task) {
+                Ok(batch) => {
+                    // an empty page may be past the end, rather than where it ends
+                    if batch.num_rows() < PAGE_SIZE && (batch.num_rows() > 0 || page == 0) {
+                        self.row_count
+                            .get_or_insert(page * PAGE_SIZE + batch.num_rows());
+                    }
+                    self.pages.insert(page, batch);
+                }
+                Err(err) => {
+                    self.reading_stopped = true;
+                    result = Err(err);
+                }
+            }
+        }
+
+        let finished = self
+            .pending_distinct_values
+            .iter()
+            .filter_map(|(col, task)| task.is_finished().then_some(col.clone()))
+            .collect::<Vec<_>>();
+        for col in finished {
+            let task = self
+                .pending_distinct_values
+                .remove(&col)
+                .expect("Values are pending");
+            let values = smol::block_on(task).unwrap_or_else(|err| {
+                result = Err(err);
+                None
+            });
+            self.distinct_values.insert(col, values);
+        }
+
+        result.map(|_| {
+            self.row_count_task.is_some()
+                || self.pending_page.is_some()
+                || self.pending_seek.is_some()
+                || !self.pending_distinct_values.is_empty()
+                || self.copy_task.is_some()
+        })
+    }
+
+    /// Get the page containing the row at `index`, along with the row's offset within it.
+    /// If the page hasn't been read, starts reading it and returns None. Rows are visited
+    /// in order, so the last page asked for is the furthest the table needs.
+    pub fn row(&mut self, index: usize) -> Option<(RecordBatch, usize)> {
+        let page = index / PAGE_SIZE;
+        match self.pages.get(&page) {
+            Some(batch) if index % PAGE_SIZE < batch.num_rows() => {
+                Some((batch.clone(), index % PAGE_SIZE))
+            }
+            Some(_) => None,
+            None => {
+                self.read_to(page);
+                None
+            }
+        }
+    }
+
+    fn read_to(&mut self, page: usize) {
+        if self.reading_stopped {
+            return;
+        }
+        if (self.next_page..=self.next_page + MAX_CACHED_PAGES).contains(&page) {
+            self.wanted_page = page;
+            self.read_next_page();
+        } else {
+            // the run keeps its place, for when the table is scrolled back to it
+            self.read_page_at(page);
+        }
+    }
+
+    /// Read a page on its own, for a page that was dropped, or one far past the run, rather than
+    /// running the query from the start or reading every page before it. The query is run again
+    /// from an offset, so see [`PageReader`] for queries without a stable order.
+    fn read_page_at(&mut self, page: usize) {
+        if self.pending_seek.is_some() {
+            return;
+        }
+        let df = (*self.df).clone();
+        let task = smol::spawn(Compat::new(with_timeout(self.timeout, async move {
+            let df = df.limit(page * PAGE_SIZE, Some(PAGE_SIZE))?;
+            let schema: SchemaRef = Arc::new(df.schema().into());
+            concat_record_batches(&schema, df.collect().await?)
+        })));
+        self.pending_seek = Some((page, task));
+    }
+
+    /// Start reading the next page of the current run, if the table needs it.
+    fn read_next_page(&mut self) {
+        if self.pending_page.is_some() || self.next_page > self.wanted_page {
+            return;
+        }
+        let reader = self.reader.take();
+        if reader.is_none() && self.next_page > 0 {
+            // the run has ended
+            return;
+        }
+        let df = self.df.clone();
+        self.pending_page = Some(smol::spawn(Compat::new(with_timeout(
+            self.timeout,
+            async move {
+                let reader = match reader {
+                    Some(reader) => reader,
+                    None => PageReader::start(*df).await?,
+                };
+                reader.read_page().await
+            },
+        ))));
+    }
+
+    /// Drop cached pages that are far from the row at `index`.
+    pub fn evict_pages(&mut self, index: usize) {
+        let current = index / PAGE_SIZE;
+        self.pages
+            .retain(|page, _| page.abs_diff(current) <= MAX_CACHED_PAGES / 2);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use datafusion::arrow::array::{ArrayRef, AsArray, BooleanArray, Int32Array, Int64Array};
+    use datafusion::arrow::datatypes::Int64Type;
+    use datafusion::arrow::util::pretty::pretty_format_batches;
+    use datafusion::parquet::arrow::ArrowWriter;
+
+    use super::*;
+
+    fn write_parquet(path: &Path, columns: Vec<(&str, ArrayRef)>) {
+        let batch = RecordBatch::try_from_iter(columns).unwrap();
+        let mut writer =
+            ArrowWriter::try_new(File::create(path).unwrap(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+    }
+
+    #[test]
+    fn reads_merged_schemas() {
+        let dir = std::env::temp_dir().join(format!("parqbench-merge-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        write_parquet(
+            &dir.join("1.parquet"),
+            vec![
+                ("id", Arc::new(Int32Array::from(vec![1, 2]))),
+                ("name", Arc::new(StringArray::from(vec!["a", "b"]))),
+            ],
+        );
+        // columns in another order, one of them wider, and one added
+        write_parquet(
+            &dir.join("2.parquet"),
+            vec![
+                ("added", Arc::new(BooleanArray::from(vec![true]))),
+                ("name", Arc::new(StringArray::from(vec!["c"]))),
+                ("id", Arc::new(Int64Array::from(vec![3]))),
+            ],
+        );
+
+        let result = smol::block_on(Compat::new(async {
+            let mut data_source = DataSource::default();
+            let source = TableDescriptor::new(dir.to_str().unwrap())?
+                .with_table_name("merged")
+                .with_merge_schemas(true);
+            data_source.add_data_source(source).await?;
+            data_source
+                .ctx
+                .sql("SELECT * FROM merged ORDER BY id")
+                .await?
+                .collect()
+                .await
+                .map_err(anyhow::Error::from)
+        }));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let result = pretty_format_batches(&result.unwrap()).unwrap().to_string();
+        assert_eq!(
+            result,
+            [
+                "+----+------+-------+",
+                "| id | name | added |",
+                "+----+------+-------+",
+                "| 1  | a    |       |",
+                "| 2  | b    |       |",
+                "| 3  | c    | true  |",
+                "+----+------+-------+",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn view_sql_reads_the_same_rows() {
+        let (shown, view) = smol::block_on(Compat::new(async {
+            let mut data_source = DataSource::default();
+            data_source
+                .ctx
+                .sql("CREATE TABLE t (id INT, \"Name\" TEXT) AS VALUES (1, 'a.b'), (2, 'it''s'), (3, 'c'), (4, NULL)")
+                .await?;
+            let data = data_source
+                .query(Query::Sql("SELECT * FROM t;".to_owned()))
+                .await?
+                .filter(vec![
+                    Filter::new(
+                        "Name",
+                        &DataType::Utf8,
+                        ColumnFilter::OneOf(vec!["a.b".to_owned(), "it's".to_owned(), "c".to_owned()]),
+                    ),
+                    Filter::new(
+                        "id",
+                        &DataType::Int32,
+                        ColumnFilter::Range {
+                            min: None,
+                            max: Some("2".to_owned()),
+                        },
+                    ),
+                    Filter::new("Name", &DataType::Utf8, ColumnFilter::Contains("'".to_owned())),
+                ])
+                .await?
+                .sort(vec![SortKey::new("Name", SortState::Descending)])
+                .await?;
+            let shown = data.dataframe().collect().await?;
+            let sql = data.view_sql().ok_or(anyhow!("No view SQL"))?;
+            data_source.register_result(
+                "v",
+                data_source.ctx.sql(&sql).await?.into_view(),
+                ResultTable::View,
+                Some(sql),
+            )?;
+            let view = data_source.ctx.sql("SELECT * FROM v").await?.collect().await?;
+            anyhow::Ok((shown, view))
+        }))
+        .unwrap();
+        assert_eq!(
+            pretty_format_batches(&view).unwrap().to_string(),
+            pretty_format_batches(&shown).unwrap().to_string()
+        );
+        assert_eq!(view.iter().map(RecordBatch::num_rows).sum::<usize>(), 1);
+    }
+
+    #[test]
+    fn descriptors_default_missing_fields() {
+        let source: TableDescriptor = toml::from_str(
+            r#"
+            url = "file:///data/rows.csv"
+            format = "Csv"
+            delimiter = ";"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(source.delimiter, b';');
+        assert!(source.has_header);
+        assert_eq!(source.schema_infer_max_records, 1000);
+        assert!(toml::to_string(&source)
+            .unwrap()
+            .contains("delimiter = \";\""));
+
+        // as written by earlier versions
+        let source: TableDescriptor =
+            toml::from_str("url = \"file:///data/rows.tsv\"\ndelimiter = 9").unwrap();
+        assert_eq!(source.delimiter, b'\t');
+        assert_eq!(source.format, TableFormat::Parquet);
+
+        assert!(toml::from_str::<TableDescriptor>("delimiter = \";;\"").is_err());
+    }
+
+    /// The id in the row at `index`, once its page has been read.
+    fn read_row(data: &mut Data, index: usize) -> i64 {
+        loop {
+            if let Some((batch, row)) = data.row(index) {
+                return batch.column(0).as_primitive::<Int64Type>().value(row);
+            }
+            data.poll().unwrap();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn reads_pages_away_from_the_run() {
+        let rows = 40 * PAGE_SIZE;
+        let batch = RecordBatch::try_from_iter([(
+            "id",
+            Arc::new(Int64Array::from_iter_values(0..rows as i64)) as ArrayRef,
+        )])
+        .unwrap();
+        let df = SessionContext::new().read_batch(batch).unwrap();
+        let mut data = Data::new(df, None);
+
+        assert_eq!(read_row(&mut data, 1), 1);
+        while data.row_count().is_none() {
+            data.poll().unwrap();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        // far past the run, which isn't read up to it
+        let far = 30 * PAGE_SIZE + 5;
+        assert_eq!(read_row(&mut data, far), far as i64);
+        let next_page = data.next_page;
+        assert!(next_page < 30);
+
+        // dropped pages are read again without starting the run over
+        data.evict_pages(far);
+        assert!(!data.pages.contains_key(&0));
+        assert_eq!(read_row(&mut data, 3), 3);
+        assert_eq!(data.next_page, next_page);
+        assert_eq!(read_row(&mut data, 2 * PAGE_SIZE), 2 * PAGE_SIZE as i64);
+    }
+
+    #[test]
+    fn merging_needs_columns_read_by_name() {
+        let source = TableDescriptor::new(std::env::temp_dir().to_str().unwrap())
+            .unwrap()
+            .with_format(TableFormat::Csv)
+            .with_merge_schemas(true);
+        let result = smol::block_on(Compat::new(async {
+            source.table(&DataSource::default().state()).await
+        }));
+        assert!(result.is_err());
     }
 }
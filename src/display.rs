@@ -0,0 +1,534 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use base64::Engine;
+use datafusion::arrow::array::timezone::Tz;
+use datafusion::arrow::array::{Array, ArrayRef, AsArray};
+use datafusion::arrow::compute::cast;
+use datafusion::arrow::datatypes::{DataType, Float64Type, TimeUnit};
+use datafusion::arrow::util::display::{array_value_to_string, ArrayFormatter, FormatOptions};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+/// Kinds of columns that share a display format.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ValueKind {
+    Integer,
+    Float,
+    Timestamp,
+    Binary,
+}
+
+impl ValueKind {
+    pub const ALL: [ValueKind; 4] = [
+        ValueKind::Integer,
+        ValueKind::Float,
+        ValueKind::Timestamp,
+        ValueKind::Binary,
+    ];
+
+    pub fn of(data_type: &DataType) -> Option<Self> {
+        match data_type {
+            data_type if data_type.is_integer() => Some(ValueKind::Integer),
+            data_type if data_type.is_floating() => Some(ValueKind::Float),
+            DataType::Decimal128(_, _) | DataType::Decimal256(_, _) => Some(ValueKind::Float),
+            DataType::Timestamp(_, _) => Some(ValueKind::Timestamp),
+            DataType::Binary
+            | DataType::LargeBinary
+            | DataType::FixedSizeBinary(_)
+            | DataType::BinaryView => Some(ValueKind::Binary),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ValueKind::Integer => "Integers",
+            ValueKind::Float => "Floats and decimals",
+            ValueKind::Timestamp => "Timestamps",
+            ValueKind::Binary => "Binary",
+        }
+    }
+}
+
+/// Unit of integers shown as times since the Unix epoch.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum EpochUnit {
+    Seconds,
+    Milliseconds,
+    Microseconds,
+    Nanoseconds,
+}
+
+impl EpochUnit {
+    pub const ALL: [EpochUnit; 4] = [
+        EpochUnit::Seconds,
+        EpochUnit::Milliseconds,
+        EpochUnit::Microseconds,
+        EpochUnit::Nanoseconds,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EpochUnit::Seconds => "Seconds since epoch",
+            EpochUnit::Milliseconds => "Milliseconds since epoch",
+            EpochUnit::Microseconds => "Microseconds since epoch",
+            EpochUnit::Nanoseconds => "Nanoseconds since epoch",
+        }
+    }
+
+    fn time_unit(&self) -> TimeUnit {
+        match self {
+            EpochUnit::Seconds => TimeUnit::Second,
+            EpochUnit::Milliseconds => TimeUnit::Millisecond,
+            EpochUnit::Microseconds => TimeUnit::Microsecond,
+            EpochUnit::Nanoseconds => TimeUnit::Nanosecond,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum BinaryFormat {
+    #[default]
+    Hex,
+    Base64,
+    /// The bytes as UTF-8, replacing invalid sequences.
+    Text,
+}
+
+impl BinaryFormat {
+    pub const ALL: [BinaryFormat; 3] =
+        [BinaryFormat::Hex, BinaryFormat::Base64, BinaryFormat::Text];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BinaryFormat::Hex => "Hex",
+            BinaryFormat::Base64 => "Base64",
+            BinaryFormat::Text => "Text",
+        }
+    }
+}
+
+/// How the values of a column are shown. Each setting only applies to the kinds of values it
+/// makes sense for, and the defaults show values as arrow displays them.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default)]
+#[serde(default)]
+pub struct ValueFormat {
+    /// Digits after the decimal point, or None to show every digit.
+    pub decimals: Option<usize>,
+    pub thousands_separator: bool,
+    pub scientific: bool,
+    /// Shows integers as timestamps, counting from the Unix epoch in this unit.
+    pub epoch: Option<EpochUnit>,
+    /// Timezone to show timestamps in, such as `Europe/Paris` or `+02:00`, or empty to show them
+    /// as they're stored.
+    pub timezone: String,
+    /// A chrono format string for timestamps, such as `%Y-%m-%d %H:%M`, or empty for ISO 8601.
+    pub timestamp_format: String,
+    pub binary: BinaryFormat,
+}
+
+static PLAIN: ValueFormat = ValueFormat {
+    decimals: None,
+    thousands_separator: false,
+    scientific: false,
+    epoch: None,
+    timezone: String::new(),
+    timestamp_format: String::new(),
+    binary: BinaryFormat::Hex,
+};
+
+/// Display formats of result cells, for each kind of column and for particular columns.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+#[serde(default)]
+pub struct DisplayFormats {
+    pub integers: ValueFormat,
+    pub floats: ValueFormat,
+    pub timestamps: ValueFormat,
+    pub binary: ValueFormat,
+    /// Formats of columns by name, used instead of the format of their kind.
+    pub columns: BTreeMap<String, ValueFormat>,
+    /// Shown in place of null values.
+    pub null: String,
+}
+
+impl Default for DisplayFormats {
+    fn default() -> Self {
+        Self {
+            integers: ValueFormat::default(),
+            floats: ValueFormat::default(),
+            timestamps: ValueFormat::default(),
+            binary: ValueFormat::default(),
+            columns: BTreeMap::new(),
+            null: "NULL".to_owned(),
+        }
+    }
+}
+
+impl DisplayFormats {
+    pub fn kind(&self, kind: ValueKind) -> &ValueFormat {
+        match kind {
+            ValueKind::Integer => &self.integers,
+            ValueKind::Float => &self.floats,
+            ValueKind::Timestamp => &self.timestamps,
+            ValueKind::Binary => &self.binary,
+        }
+    }
+
+    pub fn kind_mut(&mut self, kind: ValueKind) -> &mut ValueFormat {
+        match kind {
+            ValueKind::Integer => &mut self.integers,
+            ValueKind::Float => &mut self.floats,
+            ValueKind::Timestamp => &mut self.timestamps,
+            ValueKind::Binary => &mut self.binary,
+        }
+    }
+
+    /// Format of a column, its own if it has one, otherwise that of its kind.
+    pub fn column(&self, name: &str, data_type: &DataType) -> &ValueFormat {
+        self.columns
+            .get(name)
+            .or_else(|| ValueKind::of(data_type).map(|kind| self.kind(kind)))
+            .unwrap_or(&PLAIN)
+    }
+}
+
+/// Whether `timezone` is one timestamps can be shown in.
+pub fn is_timezone(timezone: &str) -> bool {
+    Tz::from_str(timezone).is_ok()
+}
+
+fn split_sign(number: &str) -> (&str, &str) {
+    match number.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", number),
+    }
+}
+
+/// Separate the thousands of the integer part of a formatted number with commas.
+fn group_thousands(number: &str) -> String {
+    let (sign, unsigned) = split_sign(number);
+    let digits = unsigned
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(unsigned.len());
+    let (integer, rest) = unsigned.split_at(digits);
+    let mut grouped = String::with_capacity(number.len() + digits / 3);
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (digits - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    format!("{}{}{}", sign, grouped, rest)
+}
+
+/// The first `keep` of a string of decimal digits, rounded half away from zero and padded with
+/// zeros. Rounding up nines carries into a new first digit, making the result a digit longer.
+fn round_digits(digits: &str, keep: usize) -> String {
+    let mut kept = digits
+        .bytes()
+        .chain(std::iter::repeat(b'0'))
+        .take(keep)
+        .collect_vec();
+    if digits
+        .as_bytes()
+        .get(keep)
+        .is_some_and(|&digit| digit >= b'5')
+    {
+        match kept.iter().rposition(|&digit| digit != b'9') {
+            Some(last) => {
+                kept[last] += 1;
+                kept[last + 1..].fill(b'0');
+            }
+            None => {
+                kept.fill(b'0');
+                kept.insert(0, b'1');
+            }
+        }
+    }
+    String::from_utf8(kept).expect("Digits are ASCII")
+}
+
+/// An exact number, as arrow displays integers and decimals, with `decimals` digits after the
+/// decimal point.
+fn fixed_point(number: &str, decimals: usize) -> String {
+    let (sign, unsigned) = split_sign(number);
+    let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    let digits = round_digits(
+        &format!("{}{}", integer, fraction),
+        integer.len() + decimals,
+    );
+    // rounded to zero, which has no sign
+    let sign = if digits.bytes().all(|digit| digit == b'0') {
+        ""
+    } else {
+        sign
+    };
+    let (integer, fraction) = digits.split_at(digits.len() - decimals);
+    if fraction.is_empty() {
+        format!("{}{}", sign, integer)
+    } else {
+        format!("{}{}.{}", sign, integer, fraction)
+    }
+}
+
+/// An exact number in scientific notation, as `{:e}` shows floats, with `decimals` digits after
+/// the decimal point, or as many as it has.
+fn scientific_notation(number: &str, decimals: Option<usize>) -> String {
+    let (sign, unsigned) = split_sign(number);
+    let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    let digits = format!("{}{}", integer, fraction);
+    let (sign, first, mut exponent) = match digits.find(|digit| digit != '0') {
+        Some(first) => (sign, first, integer.len() as i64 - 1 - first as i64),
+        None => ("", digits.len().saturating_sub(1), 0),
+    };
+    let mut digits = match decimals {
+        Some(decimals) => round_digits(&digits[first..], decimals + 1),
+        None => digits[first..].trim_end_matches('0').to_owned(),
+    };
+    if decimals.is_some_and(|decimals| digits.len() > decimals + 1) {
+        // rounded up to the next power of ten
+        digits.pop();
+        exponent += 1;
+    }
+    match digits.split_at(1.min(digits.len())) {
+        ("", _) => format!("0e{}", exponent),
+        (first, "") => format!("{}{}e{}", sign, first, exponent),
+        (first, rest) => format!("{}{}.{}e{}", sign, first, rest, exponent),
+    }
+}
+
+/// A number, in the first row of `value`.
+fn format_number(value: &ArrayRef, format: &ValueFormat) -> anyhow::Result<String> {
+    let text = match (format.scientific, format.decimals) {
+        (false, None) => array_value_to_string(value, 0)?,
+        // integers and decimals can be larger or more precise than a float, so are formatted
+        // from their digits
+        (scientific, decimals) if !value.data_type().is_floating() => {
+            let number = array_value_to_string(value, 0)?;
+            if scientific {
+                scientific_notation(&number, decimals)
+            } else {
+                fixed_point(&number, decimals.unwrap_or_default())
+            }
+        }
+        (scientific, decimals) => {
+            let number = cast(value, &DataType::Float64)?
+                .as_primitive::<Float64Type>()
+                .value(0);
+            match (scientific, decimals) {
+                (true, Some(decimals)) => format!("{:.*e}", decimals, number),
+                (true, None) => format!("{:e}", number),
+                (false, Some(decimals)) => format!("{:.*}", decimals, number),
+                (false, None) => number.to_string(),
+            }
+        }
+    };
+    Ok(if format.thousands_separator && !format.scientific {
+        group_thousands(&text)
+    } else {
+        text
+    })
+}
+
+/// A timestamp, in the first row of `value`.
+fn format_timestamp(value: &ArrayRef, format: &ValueFormat) -> anyhow::Result<String> {
+    let value = match value.data_type() {
+        DataType::Timestamp(unit, timezone) if !format.timezone.is_empty() => {
+            // casting to a timezone keeps the time of day of timestamps without one, so they're
+            // first marked as UTC, which they're taken to be in, to be converted from UTC
+            let value = match timezone {
+                Some(_) => value.clone(),
+                None => cast(
+                    value,
+                    &DataType::Timestamp(unit.clone(), Some("UTC".into())),
+                )?,
+            };
+            cast(
+                &value,
+                &DataType::Timestamp(unit.clone(), Some(format.timezone.as_str().into())),
+            )?
+        }
+        _ => value.clone(),
+    };
+    let pattern = (!format.timestamp_format.is_empty()).then_some(format.timestamp_format.as_str());
+    let options = FormatOptions::new()
+        .with_timestamp_format(pattern)
+        .with_timestamp_tz_format(pattern);
+    let text = ArrayFormatter::try_new(value.as_ref(), &options)?
+        .value(0)
+        .try_to_string()?;
+    Ok(text)
+}
+
+fn format_binary(value: &ArrayRef, format: &ValueFormat) -> anyhow::Result<String> {
+    let bytes = match value.data_type() {
+        DataType::Binary => value.as_binary::<i32>().value(0),
+        DataType::LargeBinary => value.as_binary::<i64>().value(0),
+        DataType::FixedSizeBinary(_) => value.as_fixed_size_binary().value(0),
+        DataType::BinaryView => value.as_binary_view().value(0),
+        _ => return Ok(array_value_to_string(value, 0)?),
+    };
+    Ok(match format.binary {
+        BinaryFormat::Hex => array_value_to_string(value, 0)?,
+        BinaryFormat::Base64 => base64::engine::general_purpose::STANDARD.encode(bytes),
+        BinaryFormat::Text => String::from_utf8_lossy(bytes).into_owned(),
+    })
+}
+
+fn try_format_value(array: &ArrayRef, row: usize, format: &ValueFormat) -> anyhow::Result<String> {
+    if *format == PLAIN {
+        return Ok(array_value_to_string(array, row)?);
+    }
+    // formatting may cast, so only the value being shown is
+    let value = array.slice(row, 1);
+    match ValueKind::of(array.data_type()) {
+        Some(ValueKind::Integer) => match format.epoch {
+            Some(unit) => format_timestamp(
+                &cast(&value, &DataType::Timestamp(unit.time_unit(), None))?,
+                format,
+            ),
+            None => format_number(&value, format),
+        },
+        Some(ValueKind::Float) => format_number(&value, format),
+        Some(ValueKind::Timestamp) => format_timestamp(&value, format),
+        Some(ValueKind::Binary) => format_binary(&value, format),
+        None => Ok(array_value_to_string(array, row)?),
+    }
+}
+
+/// Text of the value at `row` of `array`, shown as `format`. Values that can't be shown that way,
+/// such as timestamps in an unknown timezone, are shown as arrow displays them.
+pub fn format_value(array: &ArrayRef, row: usize, format: &ValueFormat) -> String {
+    try_format_value(array, row, format)
+        .or_else(|_| array_value_to_string(array, row))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use datafusion::arrow::array::{Decimal128Array, Int64Array, TimestampSecondArray};
+
+    use super::*;
+
+    #[test]
+    fn thousands() {
+        assert_eq!(group_thousands("0"), "0");
+        assert_eq!(group_thousands("999"), "999");
+        assert_eq!(group_thousands("1000"), "1,000");
+        assert_eq!(group_thousands("1234567"), "1,234,567");
+        assert_eq!(group_thousands("-1234567"), "-1,234,567");
+        assert_eq!(group_thousands("-123"), "-123");
+        assert_eq!(group_thousands("1234.5678"), "1,234.5678");
+        assert_eq!(group_thousands("-123456.7"), "-123,456.7");
+        assert_eq!(group_thousands("NaN"), "NaN");
+    }
+
+    #[test]
+    fn thousands_with_decimals() {
+        let array: ArrayRef = Arc::new(Int64Array::from(vec![-1_234_567]));
+        let format = ValueFormat {
+            decimals: Some(2),
+            thousands_separator: true,
+            ..Default::default()
+        };
+        assert_eq!(format_value(&array, 0, &format), "-1,234,567.00");
+    }
+
+    fn number(decimals: Option<usize>, scientific: bool) -> ValueFormat {
+        ValueFormat {
+            decimals,
+            scientific,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn large_integers_are_exact() {
+        let array: ArrayRef = Arc::new(Int64Array::from(vec![9_007_199_254_740_993]));
+        assert_eq!(
+            format_value(&array, 0, &number(Some(1), false)),
+            "9007199254740993.0"
+        );
+        assert_eq!(
+            format_value(&array, 0, &number(None, true)),
+            "9.007199254740993e15"
+        );
+        assert_eq!(format_value(&array, 0, &number(Some(2), true)), "9.01e15");
+    }
+
+    #[test]
+    fn decimals_are_rounded_exactly() {
+        let array: ArrayRef = Arc::new(
+            Decimal128Array::from(vec![1_005, -99_995, 0])
+                .with_precision_and_scale(10, 3)
+                .unwrap(),
+        );
+        assert_eq!(format_value(&array, 0, &number(Some(2), false)), "1.01");
+        assert_eq!(format_value(&array, 1, &number(Some(2), false)), "-100.00");
+        assert_eq!(format_value(&array, 1, &number(Some(0), false)), "-100");
+        assert_eq!(format_value(&array, 2, &number(Some(1), false)), "0.0");
+        assert_eq!(format_value(&array, 0, &number(None, true)), "1.005e0");
+        assert_eq!(format_value(&array, 1, &number(Some(1), true)), "-1.0e2");
+        assert_eq!(format_value(&array, 2, &number(None, true)), "0e0");
+    }
+
+    fn epoch(unit: EpochUnit, timezone: &str) -> ValueFormat {
+        ValueFormat {
+            epoch: Some(unit),
+            timezone: timezone.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn epoch_in_utc() {
+        let array: ArrayRef = Arc::new(Int64Array::from(vec![0]));
+        assert_eq!(
+            format_value(&array, 0, &epoch(EpochUnit::Seconds, "")),
+            "1970-01-01T00:00:00"
+        );
+    }
+
+    #[test]
+    fn epoch_in_timezone() {
+        let array: ArrayRef = Arc::new(Int64Array::from(vec![0]));
+        assert_eq!(
+            format_value(&array, 0, &epoch(EpochUnit::Seconds, "+02:00")),
+            "1970-01-01T02:00:00+02:00"
+        );
+        assert_eq!(
+            format_value(
+                &array,
+                0,
+                &epoch(EpochUnit::Milliseconds, "America/New_York")
+            ),
+            "1969-12-31T19:00:00-05:00"
+        );
+    }
+
+    #[test]
+    fn timestamp_in_timezone() {
+        let format = ValueFormat {
+            timezone: "Asia/Tokyo".to_owned(),
+            timestamp_format: "%Y-%m-%d %H:%M".to_owned(),
+            ..Default::default()
+        };
+        let naive: ArrayRef = Arc::new(TimestampSecondArray::from(vec![0]));
+        assert_eq!(format_value(&naive, 0, &format), "1970-01-01 09:00");
+        let zoned: ArrayRef = Arc::new(TimestampSecondArray::from(vec![0]).with_timezone("-01:00"));
+        assert_eq!(format_value(&zoned, 0, &format), "1970-01-01 09:00");
+    }
+
+    #[test]
+    fn unknown_timezone_shows_raw() {
+        let array: ArrayRef = Arc::new(TimestampSecondArray::from(vec![0]));
+        let format = ValueFormat {
+            timezone: "Not/A_Zone".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(format_value(&array, 0, &format), "1970-01-01T00:00:00");
+    }
+}
@@ -0,0 +1,94 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use datafusion::logical_expr::LogicalPlan;
+use datafusion::physical_plan::{display::DisplayableExecutionPlan, execute_stream, ExecutionPlan};
+use datafusion::prelude::DataFrame;
+use smol::stream::StreamExt;
+
+/// An operator of a query plan, along with the operators it reads from.
+pub struct PlanNode {
+    pub operator: String,
+    /// The operator's settings, such as its predicate or the files it reads.
+    pub details: String,
+    /// Name and value of the metrics recorded while the plan ran, empty unless it was analyzed.
+    pub metrics: Vec<(String, String)>,
+    pub children: Vec<PlanNode>,
+}
+
+/// How DataFusion runs a query, as with `EXPLAIN` or `EXPLAIN ANALYZE`.
+pub struct QueryPlan {
+    pub logical: PlanNode,
+    pub physical: PlanNode,
+    /// Time to run the whole plan, if it was analyzed.
+    pub elapsed: Option<Duration>,
+}
+
+/// Splits `Operator: details` as both plans display a node on one line.
+fn split_operator(line: &str) -> (String, String) {
+    match line.split_once(": ") {
+        Some((operator, details)) => (operator.to_owned(), details.to_owned()),
+        None => (line.to_owned(), String::new()),
+    }
+}
+
+fn logical_node(plan: &LogicalPlan) -> PlanNode {
+    let (operator, details) = split_operator(&plan.display().to_string());
+    PlanNode {
+        operator,
+        details,
+        metrics: vec![],
+        children: plan.inputs().into_iter().map(logical_node).collect(),
+    }
+}
+
+fn physical_node(plan: &Arc<dyn ExecutionPlan>) -> PlanNode {
+    let line = DisplayableExecutionPlan::new(plan.as_ref())
+        .one_line()
+        .to_string();
+    let (operator, details) = split_operator(line.trim_end());
+    let metrics = plan
+        .metrics()
+        .map(|metrics| {
+            // summed across partitions, as EXPLAIN ANALYZE shows them
+            metrics
+                .aggregate_by_name()
+                .sorted_for_display()
+                .timestamps_removed()
+                .iter()
+                .map(|metric| (metric.value().name().to_owned(), metric.value().to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
+    PlanNode {
+        operator,
+        details,
+        metrics,
+        children: plan.children().into_iter().map(physical_node).collect(),
+    }
+}
+
+/// Plan the query of `df`. If `analyze` is set the plan is also run, discarding its rows, so that
+/// each operator reports the rows it produced, the time it took and what it pruned.
+pub async fn explain(df: DataFrame, analyze: bool) -> anyhow::Result<QueryPlan> {
+    let task_ctx = Arc::new(df.task_ctx());
+    let logical = logical_node(&df.clone().into_optimized_plan()?);
+    let plan = df.create_physical_plan().await?;
+
+    let elapsed = if analyze {
+        let start = Instant::now();
+        let mut stream = execute_stream(plan.clone(), task_ctx)?;
+        while let Some(batch) = stream.next().await {
+            batch?;
+        }
+        Some(start.elapsed())
+    } else {
+        None
+    };
+
+    Ok(QueryPlan {
+        logical,
+        physical: physical_node(&plan),
+        elapsed,
+    })
+}
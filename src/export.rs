@@ -0,0 +1,195 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::data::TableFormat;
+use anyhow::anyhow;
+use async_compat::Compat;
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::arrow::ipc::writer::FileWriter;
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::arrow::{csv, json};
+use datafusion::execution::object_store::ObjectStoreUrl;
+use datafusion::parquet::arrow::ArrowWriter;
+use datafusion::parquet::basic::Compression;
+use datafusion::parquet::file::properties::WriterProperties;
+use datafusion::prelude::DataFrame;
+use object_store::WriteMultipart;
+use smol::stream::StreamExt;
+use smol::Task;
+use url::Url;
+
+/// Parts of the file being uploaded at once.
+const MAX_CONCURRENT_UPLOADS: usize = 8;
+
+pub struct ExportOptions {
+    pub path: String,
+    pub format: TableFormat,
+    // only used by parquet
+    pub compression: Compression,
+    pub row_group_size: usize,
+}
+
+/// An export of a query result running in the background.
+pub struct Export {
+    pub path: String,
+    pub total_rows: Option<usize>,
+    rows_written: Arc<AtomicUsize>,
+    task: Task<anyhow::Result<()>>,
+}
+
+/// Buffer shared between a synchronous writer and the upload that it's drained into.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0
+            .lock()
+            .expect("Export buffer poisoned")
+            .extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SharedBuffer {
+    fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.0.lock().expect("Export buffer poisoned"))
+    }
+}
+
+enum BatchWriter {
+    Parquet(ArrowWriter<SharedBuffer>),
+    // the csv writer holds its formatting options inline, so is much larger than the others
+    Csv(Box<csv::Writer<SharedBuffer>>),
+    Json(json::LineDelimitedWriter<SharedBuffer>),
+    Arrow(FileWriter<SharedBuffer>),
+}
+
+impl BatchWriter {
+    fn new(
+        options: &ExportOptions,
+        schema: SchemaRef,
+        buffer: SharedBuffer,
+    ) -> anyhow::Result<Self> {
+        Ok(match options.format {
+            TableFormat::Parquet => {
+                let properties = WriterProperties::builder()
+                    .set_compression(options.compression)
+                    .set_max_row_group_size(options.row_group_size)
+                    .build();
+                BatchWriter::Parquet(ArrowWriter::try_new(buffer, schema, Some(properties))?)
+            }
+            TableFormat::Csv => BatchWriter::Csv(Box::new(csv::Writer::new(buffer))),
+            TableFormat::Json => BatchWriter::Json(json::LineDelimitedWriter::new(buffer)),
+            TableFormat::Arrow => BatchWriter::Arrow(FileWriter::try_new(buffer, &schema)?),
+            TableFormat::Avro => return Err(anyhow!("Exporting to Avro is not supported")),
+        })
+    }
+
+    fn write(&mut self, batch: &RecordBatch) -> anyhow::Result<()> {
+        match self {
+            BatchWriter::Parquet(writer) => writer.write(batch)?,
+            BatchWriter::Csv(writer) => writer.write(batch)?,
+            BatchWriter::Json(writer) => writer.write(batch)?,
+            BatchWriter::Arrow(writer) => writer.write(batch)?,
+        };
+        Ok(())
+    }
+
+    fn finish(self) -> anyhow::Result<()> {
+        match self {
+            BatchWriter::Parquet(writer) => {
+                writer.close()?;
+            }
+            BatchWriter::Csv(_) => {}
+            BatchWriter::Json(mut writer) => writer.finish()?,
+            BatchWriter::Arrow(mut writer) => writer.finish()?,
+        };
+        Ok(())
+    }
+}
+
+fn make_output_url(path: &str) -> anyhow::Result<Url> {
+    let path = shellexpand::full(path)?.into_owned();
+    match Url::parse(&path) {
+        // single letter schemes are windows drive letters, not urls
+        Ok(url) if url.scheme().len() > 1 => Ok(url),
+        _ => Url::from_file_path(std::env::current_dir()?.join(path))
+            .map_err(|_| anyhow!("Could not create Url from path.")),
+    }
+}
+
+async fn write_export(
+    df: DataFrame,
+    options: ExportOptions,
+    rows_written: Arc<AtomicUsize>,
+) -> anyhow::Result<()> {
+    let url = make_output_url(&options.path)?;
+    // any store registered when adding a source can be written to
+    let store_url =
+        ObjectStoreUrl::parse(&url[url::Position::BeforeScheme..url::Position::BeforePath])?;
+    let store = df.task_ctx().runtime_env().object_store(store_url)?;
+    let location = object_store::path::Path::from_url_path(url.path())?;
+
+    let mut stream = df.execute_stream().await?;
+    let buffer = SharedBuffer::default();
+    let mut writer = BatchWriter::new(&options, stream.schema(), buffer.clone())?;
+    let mut upload = WriteMultipart::new(store.put_multipart(&location).await?);
+
+    let result: anyhow::Result<()> = async {
+        while let Some(batch) = stream.next().await {
+            let batch = batch?;
+            writer.write(&batch)?;
+            rows_written.fetch_add(batch.num_rows(), Ordering::Relaxed);
+            upload.write(&buffer.take());
+            upload.wait_for_capacity(MAX_CONCURRENT_UPLOADS).await?;
+        }
+        writer.finish()?;
+        upload.write(&buffer.take());
+        Ok(())
+    }
+    .await;
+
+    match result {
+        Ok(_) => {
+            upload.finish().await?;
+            Ok(())
+        }
+        Err(err) => {
+            // the failure to write is more useful than any failure to clean up
+            let _ = upload.abort().await;
+            Err(err)
+        }
+    }
+}
+
+impl Export {
+    pub fn new(df: DataFrame, total_rows: Option<usize>, options: ExportOptions) -> Self {
+        let rows_written = Arc::new(AtomicUsize::new(0));
+        let path = options.path.clone();
+        let progress = rows_written.clone();
+        Self {
+            path,
+            total_rows,
+            rows_written,
+            task: smol::spawn(Compat::new(write_export(df, options, progress))),
+        }
+    }
+
+    pub fn rows_written(&self) -> usize {
+        self.rows_written.load(Ordering::Relaxed)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    pub fn resolve(self) -> anyhow::Result<()> {
+        smol::block_on(self.task)
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+/// Key the history is persisted under in eframe storage.
+pub const STORAGE_KEY: &str = "query_history";
+/// Queries kept in the history, the oldest are dropped first.
+const MAX_HISTORY: usize = 500;
+
+/// A query that was run from the query editor.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub id: u64,
+    pub sql: String,
+    pub started: SystemTime,
+    /// Time until the first rows were read. None if the query failed or was changed before then.
+    pub duration: Option<Duration>,
+    pub rows: Option<usize>,
+    pub tables: Vec<String>,
+    pub error: Option<String>,
+}
+
+impl HistoryEntry {
+    /// Whether `search` appears in the query or the tables it read, ignoring case.
+    pub fn matches(&self, search: &str) -> bool {
+        let search = search.to_lowercase();
+        self.sql.to_lowercase().contains(&search)
+            || self
+                .tables
+                .iter()
+                .any(|table| table.to_lowercase().contains(&search))
+    }
+}
+
+/// A named query, kept until it's deleted.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SavedQuery {
+    pub name: String,
+    /// Empty for queries that aren't in a folder.
+    pub folder: String,
+    pub sql: String,
+}
+
+/// Queries run in this and previous sessions, along with any that were saved.
+#[derive(Default, Serialize, Deserialize)]
+pub struct QueryHistory {
+    pub entries: VecDeque<HistoryEntry>,
+    pub saved: Vec<SavedQuery>,
+    next_id: u64,
+    #[serde(skip)]
+    pub search: String,
+}
+
+impl QueryHistory {
+    /// Add a query that has just been submitted, returning the id to complete it with.
+    pub fn record(&mut self, sql: &str) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.entries.push_back(HistoryEntry {
+            id,
+            sql: sql.trim().to_owned(),
+            started: SystemTime::now(),
+            duration: None,
+            rows: None,
+            tables: vec![],
+            error: None,
+        });
+        while self.entries.len() > MAX_HISTORY {
+            self.entries.pop_front();
+        }
+        id
+    }
+
+    /// The entry with `id`, unless it has since been dropped from the history.
+    pub fn entry_mut(&mut self, id: u64) -> Option<&mut HistoryEntry> {
+        self.entries.iter_mut().find(|entry| entry.id == id)
+    }
+
+    /// Save a query, replacing the one with the same name in the same folder.
+    pub fn save(&mut self, query: SavedQuery) {
+        match self
+            .saved
+            .iter_mut()
+            .find(|saved| saved.name == query.name && saved.folder == query.folder)
+        {
+            Some(saved) => saved.sql = query.sql,
+            None => self.saved.push(query),
+        }
+    }
+
+    /// Folders of saved queries, in order.
+    pub fn folders(&self) -> Vec<&str> {
+        let mut folders = self
+            .saved
+            .iter()
+            .map(|query| query.folder.as_str())
+            .filter(|folder| !folder.is_empty())
+            .collect::<Vec<_>>();
+        folders.sort();
+        folders.dedup();
+        folders
+    }
+}
@@ -1,24 +1,42 @@
 use eframe;
 use egui::Layout;
+use egui_dock::{DockArea, DockState, NodeIndex, SurfaceIndex, TabViewer};
 
 use crate::{
-    components::{Action, ErrorLog, Popover, QueryBuilder, Show, ShowMut},
-    data::{Data, DataResult, DataSource, Query, TableDescriptor},
+    columns::ColumnLayout,
+    components::{
+        Action, CellInspector, ChartPanel, ErrorLog, FileDetails, PlanViewer, Popover,
+        ProfilePanel, QueryBuilder, Show, ShowMut, WorkspaceDialog,
+    },
+    data::{
+        parquet_metadata, with_timeout, Data, DataResult, DataSource, Query, ResultTable,
+        TableDescriptor,
+    },
+    display::DisplayFormats,
+    explain::explain,
+    export::Export,
+    history::{QueryHistory, STORAGE_KEY},
+    workspace::{SavedView, View, Workspace},
 };
+use anyhow::anyhow;
 use async_compat::Compat;
 use core::default::Default;
+use serde::{Deserialize, Serialize};
 use smol::lock::RwLock;
 use smol::Task;
 use std::{
+    collections::BTreeMap,
     mem,
     sync::{
         mpsc::{channel, Receiver, Sender},
         Arc,
     },
+    time::{Duration, Instant},
 };
 
 enum DataContainer {
-    Some(Data),
+    // boxed, as data is much larger than a pending task
+    Some(Box<Data>),
     Pending(Task<DataResult>),
     None,
 }
@@ -27,7 +45,7 @@ impl DataContainer {
     fn apply(&mut self, apply: impl FnOnce(Data) -> Task<DataResult>) {
         let old = mem::replace(self, DataContainer::None);
         *self = match old {
-            DataContainer::Some(data) => Self::Pending(apply(data)),
+            DataContainer::Some(data) => Self::Pending(apply(*data)),
             _ => old,
         };
     }
@@ -50,32 +68,345 @@ impl DataContainer {
     }
 }
 
+/// Read the files of `table` without holding the data source, which the side panel locks every
+/// frame, and then register it.
+async fn add_data_source(
+    data_source: &RwLock<DataSource>,
+    table: TableDescriptor,
+) -> anyhow::Result<String> {
+    let state = data_source.read().await.state();
+    let (provider, schema_report) = table.table(&state).await?;
+    data_source
+        .write()
+        .await
+        .register_source(table, provider, schema_report)
+}
+
+/// Register a view saved in a workspace from its SQL.
+async fn register_view(data_source: &RwLock<DataSource>, view: SavedView) -> anyhow::Result<()> {
+    let df = data_source
+        .read()
+        .await
+        .dataframe(Query::Sql(view.sql.clone()))
+        .await?;
+    let table = ResultTable::View.table(df, None).await?;
+    data_source
+        .write()
+        .await
+        .register_result(&view.name, table, ResultTable::View, Some(view.sql))
+}
+
+/// A query result shown in its own tab of the central panel.
+struct ResultTab {
+    id: usize,
+    title: String,
+    query: Query,
+    data: DataContainer,
+    profile: Option<ProfilePanel>,
+    chart: Option<ChartPanel>,
+    inspector: Option<CellInspector>,
+    started: Instant,
+    // entry in the query history to complete once the first rows are read and the rows counted
+    history_id: Option<u64>,
+}
+
+impl ResultTab {
+    fn apply(&mut self, apply: impl FnOnce(Data) -> Task<DataResult>) {
+        // the history describes the query as submitted, so stops following it once it's changed
+        self.history_id = None;
+        self.data.apply(apply);
+    }
+
+    /// Drop the running query, which stops its execution.
+    fn cancel(&mut self) {
+        // replaced by a query that fails, so the cancellation is logged and kept in the history
+        self.data = DataContainer::Pending(smol::spawn(async { Err(anyhow!("Query cancelled")) }));
+    }
+
+    fn update_history(&mut self, history: &mut QueryHistory, error: Option<&anyhow::Error>) {
+        let Some(entry) = self.history_id.and_then(|id| history.entry_mut(id)) else {
+            self.history_id = None;
+            return;
+        };
+        match &self.data {
+            DataContainer::Pending(_) => {}
+            DataContainer::None => {
+                entry.error = error.map(|err| err.to_string());
+                self.history_id = None;
+            }
+            DataContainer::Some(data) => {
+                // the query is timed by its first rows, as counting them runs it again
+                if let Some(read) = data.first_page_read() {
+                    entry.duration = Some(read.duration_since(self.started));
+                }
+                entry.rows = data.row_count();
+                // done once the rows are counted and the first are read, or either fails or stops
+                if !data.is_counting() && (entry.duration.is_some() || data.is_reading_stopped()) {
+                    entry.tables = data.table_names();
+                    self.history_id = None;
+                }
+            }
+        }
+    }
+
+    /// Check whether the query has finished, arranging the columns of a table as they were left.
+    fn check_data_future(
+        &mut self,
+        column_layouts: &BTreeMap<String, ColumnLayout>,
+    ) -> Option<anyhow::Error> {
+        match self.data.try_resolve()? {
+            Ok(data) => {
+                let data = match &self.query {
+                    Query::TableName(table_name) => match column_layouts.get(table_name) {
+                        Some(columns) => data.with_columns(columns.clone()),
+                        None => data,
+                    },
+                    Query::Sql(_) => data,
+                };
+                self.data = DataContainer::Some(Box::new(data));
+                None
+            }
+            Err(err) => {
+                self.data = DataContainer::None;
+                Some(err)
+            }
+        }
+    }
+}
+
+/// Shows result tabs, collecting any actions for the app to handle once the dock is drawn.
+struct ResultTabViewer<'a> {
+    actions: Vec<Action>,
+    formats: &'a DisplayFormats,
+}
+
+impl TabViewer for ResultTabViewer<'_> {
+    type Tab = ResultTab;
+
+    fn title(&mut self, tab: &mut Self::Tab) -> egui::WidgetText {
+        tab.title.as_str().into()
+    }
+
+    fn id(&mut self, tab: &mut Self::Tab) -> egui::Id {
+        egui::Id::new(("result_tab", tab.id))
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, tab: &mut Self::Tab) {
+        // scroll and column widths are keyed on the tab, rather than its title
+        ui.push_id(tab.id, |ui| {
+            if let Some(profile) = &mut tab.profile {
+                let action = egui::SidePanel::right(egui::Id::new(("profile", tab.id)))
+                    .resizable(true)
+                    .default_width(ui.style().spacing.text_edit_width * 1.5)
+                    .show_inside(ui, |ui| {
+                        egui::ScrollArea::vertical()
+                            .show(ui, |ui| profile.show(ui))
+                            .inner
+                    })
+                    .inner;
+                self.actions.extend(action);
+                if !profile.is_open() {
+                    tab.profile = None;
+                }
+            }
+            if let Some(chart) = &mut tab.chart {
+                let action = egui::TopBottomPanel::bottom(egui::Id::new(("chart", tab.id)))
+                    .resizable(true)
+                    .default_height(ui.available_height() / 2.0)
+                    .show_inside(ui, |ui| chart.show(ui))
+                    .inner;
+                self.actions.extend(action);
+                if !chart.is_open() {
+                    tab.chart = None;
+                }
+            }
+            if let Query::Sql(sql) = &tab.query {
+                egui::CollapsingHeader::new("Query").show(ui, |ui| {
+                    ui.label(egui::RichText::new(sql).monospace());
+                });
+            }
+            let action = match tab.data {
+                DataContainer::Some(ref mut data) => data.show(ui, self.formats),
+                DataContainer::Pending(_) => {
+                    let cancel = ui
+                        .vertical_centered(|ui| {
+                            ui.add_space(ui.available_height() / 3.0);
+                            ui.spinner();
+                            ui.button("Cancel").clicked()
+                        })
+                        .inner;
+                    if cancel {
+                        tab.cancel();
+                    }
+                    None
+                }
+                DataContainer::None => {
+                    ui.centered_and_justified(|ui| {
+                        ui.label("No data, see the error log");
+                    });
+                    None
+                }
+            };
+            let action = match &mut tab.inspector {
+                Some(inspector) => {
+                    let (open, inspector_action) = inspector.popover(ui.ctx());
+                    if !open {
+                        tab.inspector = None;
+                    }
+                    action.or(inspector_action)
+                }
+                None => action,
+            };
+            match action {
+                // sorting and filtering only affect the data in this tab
+                Some(Action::SortData(sort)) => {
+                    tab.apply(|data| smol::spawn(async move { data.sort(sort).await }));
+                }
+                Some(Action::FilterData(filters)) => {
+                    tab.apply(|data| smol::spawn(async move { data.filter(filters).await }));
+                }
+                Some(Action::ProfileColumn(col)) => {
+                    if let DataContainer::Some(data) = &tab.data {
+                        match data.unsorted_dataframe() {
+                            Ok(df) => tab.profile = Some(ProfilePanel::new(df, col)),
+                            Err(err) => self.actions.push(Action::LogError(err)),
+                        }
+                    }
+                }
+                Some(Action::ChartColumn(col)) => {
+                    if let DataContainer::Some(data) = &tab.data {
+                        match data.unsorted_dataframe() {
+                            Ok(df) => tab.chart = Some(ChartPanel::new(df, col)),
+                            Err(err) => self.actions.push(Action::LogError(err)),
+                        }
+                    }
+                }
+                Some(Action::ArrangeColumns(columns)) => {
+                    if let DataContainer::Some(data) = &mut tab.data {
+                        // selections hold the positions of columns, which have moved, unless
+                        // they're of every column
+                        if data.columns != columns
+                            && !data.selection.is_some_and(|selection| selection.whole_rows)
+                        {
+                            data.selection = None;
+                        }
+                        data.columns = columns.clone();
+                    }
+                    // only the columns of tables are kept, as queries change between runs
+                    if let Query::TableName(table_name) = &tab.query {
+                        self.actions
+                            .push(Action::SaveColumnLayout((table_name.clone(), columns)));
+                    }
+                }
+                Some(Action::InspectCell(inspector)) => tab.inspector = Some(inspector),
+                Some(Action::ExpandStruct(col)) => {
+                    tab.apply(|data| smol::spawn(async move { data.expand_struct(col).await }));
+                }
+                Some(Action::ExplodeList(col)) => {
+                    tab.apply(|data| smol::spawn(async move { data.explode_list(col).await }));
+                }
+                Some(action) => self.actions.push(action),
+                None => {}
+            }
+        });
+    }
+
+    fn context_menu(
+        &mut self,
+        ui: &mut egui::Ui,
+        tab: &mut Self::Tab,
+        _surface: SurfaceIndex,
+        _node: NodeIndex,
+    ) {
+        ui.label("Rename");
+        ui.text_edit_singleline(&mut tab.title);
+    }
+
+    fn on_tab_button(&mut self, tab: &mut Self::Tab, response: &egui::Response) {
+        let text = match &tab.query {
+            Query::Sql(sql) => sql,
+            Query::TableName(table_name) => table_name,
+        };
+        response.clone().on_hover_text(text);
+    }
+
+    fn scroll_bars(&self, _tab: &Self::Tab) -> [bool; 2] {
+        // the table scrolls itself
+        [false, false]
+    }
+}
+
 #[derive(Default)]
 struct DisplayStates {
     error: bool,
     settings: bool,
 }
 
+/// Key the settings are persisted under in eframe storage.
+const SETTINGS_KEY: &str = "settings";
+
+/// Key the column layouts of tables are persisted under in eframe storage.
+const COLUMN_LAYOUTS_KEY: &str = "column_layouts";
+
+/// Preferences from the settings window, kept between sessions.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct Settings {
+    /// Seconds each query may run for, 0 for no limit.
+    query_timeout: u64,
+    formats: DisplayFormats,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            query_timeout: 300,
+            formats: DisplayFormats::default(),
+        }
+    }
+}
+
+impl Settings {
+    fn query_timeout(&self) -> Option<Duration> {
+        (self.query_timeout > 0).then(|| Duration::from_secs(self.query_timeout))
+    }
+}
+
 pub struct ParqBenchApp {
     data_source: Arc<RwLock<DataSource>>,
-    current_data: DataContainer,
+    tabs: DockState<ResultTab>,
+    next_tab_id: usize,
     query: QueryBuilder,
     popover: Option<Box<dyn Popover>>,
     error_log_channel: (Sender<anyhow::Error>, Receiver<anyhow::Error>),
     errors: ErrorLog,
     display_states: DisplayStates,
+    exports: Vec<Export>,
+    history: QueryHistory,
+    settings: Settings,
+    // how the columns of each table were arranged, by table name
+    column_layouts: BTreeMap<String, ColumnLayout>,
+    // views of a workspace being opened, shown once its sources are added
+    workspace_views: Option<Task<Vec<View>>>,
 }
 
 impl Default for ParqBenchApp {
     fn default() -> Self {
+        let data_source = DataSource::default();
         Self {
-            data_source: Arc::new(RwLock::new(DataSource::default())),
-            query: QueryBuilder::default(),
-            current_data: DataContainer::None,
+            query: QueryBuilder::new(data_source.function_names()),
+            data_source: Arc::new(RwLock::new(data_source)),
+            tabs: DockState::new(vec![]),
+            next_tab_id: 0,
             popover: None,
             error_log_channel: channel(),
             errors: vec![],
             display_states: DisplayStates::default(),
+            exports: vec![],
+            history: QueryHistory::default(),
+            settings: Settings::default(),
+            column_layouts: BTreeMap::new(),
+            workspace_views: None,
         }
     }
 }
@@ -83,7 +414,46 @@ impl Default for ParqBenchApp {
 impl ParqBenchApp {
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         cc.egui_ctx.set_visuals(egui::style::Visuals::dark());
-        Default::default()
+        let app = Self {
+            history: cc
+                .storage
+                .and_then(|storage| eframe::get_value(storage, STORAGE_KEY))
+                .unwrap_or_default(),
+            settings: cc
+                .storage
+                .and_then(|storage| eframe::get_value(storage, SETTINGS_KEY))
+                .unwrap_or_default(),
+            column_layouts: cc
+                .storage
+                .and_then(|storage| eframe::get_value(storage, COLUMN_LAYOUTS_KEY))
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+        app.data_source
+            .write_blocking()
+            .set_query_timeout(app.settings.query_timeout());
+        app
+    }
+
+    fn open_tab(
+        &mut self,
+        title: String,
+        query: Query,
+        task: Task<DataResult>,
+        history_id: Option<u64>,
+    ) {
+        self.next_tab_id += 1;
+        self.tabs.push_to_focused_leaf(ResultTab {
+            id: self.next_tab_id,
+            title,
+            query,
+            data: DataContainer::Pending(task),
+            profile: None,
+            chart: None,
+            inspector: None,
+            started: Instant::now(),
+            history_id,
+        });
     }
 
     pub fn handle_action(&mut self, action: Action) {
@@ -92,7 +462,7 @@ impl ParqBenchApp {
                 let data_source = self.data_source.clone();
                 let channel = self.error_log_channel.0.clone();
                 smol::spawn(Compat::new(async move {
-                    if let Err(err) = data_source.write().await.add_data_source(table).await {
+                    if let Err(err) = add_data_source(&data_source, table).await {
                         // if the channel is closed, not much we can do
                         let _ = channel.send(err);
                     }
@@ -100,33 +470,126 @@ impl ParqBenchApp {
                 .detach();
             }
             Action::QuerySource(query) => {
-                // TODO: use apply
+                let title = match query {
+                    Query::TableName(ref table_name) => table_name.clone(),
+                    Query::Sql(_) => format!("Query {}", self.next_tab_id + 1),
+                };
+                let history_id = match query {
+                    Query::Sql(ref sql) => Some(self.history.record(sql)),
+                    Query::TableName(_) => None,
+                };
                 let data_source = self.data_source.clone();
-                self.current_data = DataContainer::Pending(smol::spawn(Compat::new(async move {
-                    data_source.read().await.query(query).await
-                })));
+                let task_query = query.clone();
+                let task = smol::spawn(Compat::new(async move {
+                    data_source.read().await.query(task_query).await
+                }));
+                self.open_tab(title, query, task, history_id);
             }
             Action::LoadSource(table) => {
-                // TODO: use apply
+                let table_name = table.table_name();
                 let data_source = self.data_source.clone();
-                self.current_data = DataContainer::Pending(smol::spawn(Compat::new(async move {
-                    let table_name = data_source.write().await.add_data_source(table).await?;
-                    dbg!(&table_name);
+                let task = smol::spawn(Compat::new(async move {
+                    let table_name = add_data_source(&data_source, table).await?;
                     data_source
                         .read()
                         .await
                         .query(Query::TableName(table_name))
                         .await
-                })));
+                }));
+                self.open_tab(table_name.clone(), Query::TableName(table_name), task, None);
+            }
+            Action::InspectSource(table_name) => {
+                let data_source = self.data_source.clone();
+                let table = table_name.clone();
+                let task = smol::spawn(Compat::new(async move {
+                    // every footer is fetched, so the data source isn't held while reading
+                    let (provider, state) = {
+                        let data_source = data_source.read().await;
+                        (
+                            data_source.table_provider(&table).await?,
+                            data_source.state(),
+                        )
+                    };
+                    parquet_metadata(provider.as_ref(), &state).await
+                }));
+                self.popover = Some(Box::new(FileDetails::new(table_name, task)));
             }
-            Action::SortData((col, sort_state)) => {
-                self.current_data
-                    .apply(|data| smol::spawn(async move { data.sort(col, sort_state).await }));
+            Action::ExportData(export) => {
+                self.exports.push(export);
+            }
+            Action::SaveResult((table_name, df, kind, sql)) => {
+                let data_source = self.data_source.clone();
+                let channel = self.error_log_channel.0.clone();
+                smol::spawn(Compat::new(async move {
+                    let timeout = data_source.read().await.query_timeout();
+                    // snapshots are collected before taking the lock, which the side panel needs
+                    let result = match kind.table(*df, timeout).await {
+                        Ok(table) => {
+                            data_source
+                                .write()
+                                .await
+                                .register_result(&table_name, table, kind, sql)
+                        }
+                        Err(err) => Err(err),
+                    };
+                    if let Err(err) = result {
+                        let _ = channel.send(err);
+                    }
+                }))
+                .detach();
+            }
+            Action::SortData(_)
+            | Action::FilterData(_)
+            | Action::ProfileColumn(_)
+            | Action::ChartColumn(_)
+            | Action::ArrangeColumns(_)
+            | Action::InspectCell(_)
+            | Action::ExpandStruct(_)
+            | Action::ExplodeList(_) => {
+                // these act on the data of a single tab, so are handled by ResultTabViewer
+            }
+            Action::EditQuery(sql) => {
+                self.query.set_query(sql);
+            }
+            Action::SaveQuery(query) => {
+                self.history.save(query);
+            }
+            Action::CompareTables(comparison) => {
+                let title = format!("{} ⇄ {}", comparison.left, comparison.right);
+                let query = Query::Sql(comparison.sql());
+                let data_source = self.data_source.clone();
+                let task = smol::spawn(Compat::new(async move {
+                    let data_source = data_source.read().await;
+                    let timeout = data_source.query_timeout();
+                    let df = data_source.dataframe(Query::Sql(comparison.sql())).await?;
+                    // counting joins both tables in full, which mustn't hold up the side panel
+                    drop(data_source);
+                    Data::compare(df, *comparison, timeout).await
+                }));
+                self.open_tab(title, query, task, None);
+            }
+            Action::ExplainQuery((sql, analyze)) => {
+                let data_source = self.data_source.clone();
+                let query = Query::Sql(sql.clone());
+                let task = smol::spawn(Compat::new(async move {
+                    let data_source = data_source.read().await;
+                    let timeout = data_source.query_timeout();
+                    let df = with_timeout(timeout, data_source.dataframe(query)).await?;
+                    // analyzing runs the whole query, which mustn't hold up the side panel's lock
+                    drop(data_source);
+                    with_timeout(timeout, explain(df, analyze)).await
+                }));
+                self.popover = Some(Box::new(PlanViewer::new(sql, analyze, task)));
+            }
+            Action::OpenWorkspace(path) => match Workspace::load(&path) {
+                Ok(workspace) => self.open_workspace(workspace),
+                Err(err) => self.errors.push(err),
+            },
+            Action::SaveWorkspace(path) => {
+                if let Err(err) = self.workspace().save(&path) {
+                    self.errors.push(err);
+                }
             }
-            // if let DataContainer::Some(data) = self.current_data {
-            //     self.current_data =
-            //         DataContainer::Pending(smol::spawn(Compat::new));
-            // }
             Action::ShowPopover(popover) => {
                 self.popover = Some(popover);
             }
@@ -151,11 +614,89 @@ impl ParqBenchApp {
                     .rename_data_source(&from_name, &to_name)
                 {
                     self.errors.push(err);
+                } else if let Some(columns) = self.column_layouts.remove(&from_name) {
+                    self.column_layouts.insert(to_name, columns);
                 };
             }
+            Action::SaveColumnLayout((table_name, columns)) => {
+                if columns == ColumnLayout::default() {
+                    self.column_layouts.remove(&table_name);
+                } else {
+                    self.column_layouts.insert(table_name, columns);
+                }
+            }
         };
     }
 
+    fn workspace(&self) -> Workspace {
+        let data_source = self.data_source.read_blocking();
+        Workspace {
+            query: self.query.query().to_owned(),
+            sources: data_source.table_descriptors(),
+            saved_views: data_source
+                .view_sql()
+                .iter()
+                .map(|(name, sql)| SavedView {
+                    name: name.clone(),
+                    sql: sql.clone(),
+                })
+                .collect(),
+            views: self
+                .tabs
+                .iter_all_tabs()
+                .map(|(_, tab)| View {
+                    title: tab.title.clone(),
+                    query: tab.query.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Add the sources of a workspace and replace the open results with its views.
+    fn open_workspace(&mut self, workspace: Workspace) {
+        self.query.set_query(workspace.query);
+        self.tabs = DockState::new(vec![]);
+
+        // the views are opened by check_workspace once every source is added
+        let data_source = self.data_source.clone();
+        let channel = self.error_log_channel.0.clone();
+        self.workspace_views = Some(smol::spawn(Compat::new(async move {
+            for table in workspace.sources {
+                if let Err(err) = add_data_source(&data_source, table).await {
+                    // if the channel is closed, not much we can do
+                    let _ = channel.send(err);
+                }
+            }
+            for view in workspace.saved_views {
+                if let Err(err) = register_view(&data_source, view).await {
+                    let _ = channel.send(err);
+                }
+            }
+            workspace.views
+        })));
+    }
+
+    fn check_workspace(&mut self) -> bool {
+        if !self
+            .workspace_views
+            .as_ref()
+            .is_some_and(|task| task.is_finished())
+        {
+            return self.workspace_views.is_some();
+        }
+        let task = self.workspace_views.take().expect("Checked above");
+        for view in smol::block_on(task) {
+            let data_source = self.data_source.clone();
+            let query = view.query.clone();
+            let task = smol::spawn(Compat::new(async move {
+                data_source.read().await.query(query).await
+            }));
+            self.open_tab(view.title, view.query, task, None);
+        }
+        // the views' queries are checked from the next frame
+        true
+    }
+
     fn check_error_channel(&mut self) {
         if let Ok(err) = self.error_log_channel.1.try_recv() {
             self.handle_action(Action::LogError(err));
@@ -170,6 +711,25 @@ impl ParqBenchApp {
                 egui::ScrollArea::vertical()
                     .auto_shrink(false)
                     .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Query timeout");
+                            let response = ui
+                                .add(
+                                    egui::DragValue::new(&mut self.settings.query_timeout)
+                                        .suffix(" s"),
+                                )
+                                .on_hover_text("0 lets queries run until they finish");
+                            if response.changed() {
+                                self.data_source
+                                    .write_blocking()
+                                    .set_query_timeout(self.settings.query_timeout());
+                            }
+                        });
+                        ui.separator();
+                        egui::CollapsingHeader::new("Display Formats").show(ui, |ui| {
+                            self.settings.formats.show(ui);
+                        });
+                        ui.separator();
                         ctx.style_ui(ui);
                     });
             });
@@ -191,23 +751,42 @@ impl ParqBenchApp {
         }
     }
 
+    fn check_exports(&mut self) -> bool {
+        let (finished, running): (Vec<_>, Vec<_>) = mem::take(&mut self.exports)
+            .into_iter()
+            .partition(Export::is_finished);
+        self.exports = running;
+        for export in finished {
+            if let Err(err) = export.resolve() {
+                self.handle_action(Action::LogError(err));
+            }
+        }
+        !self.exports.is_empty()
+    }
+
     fn check_data_future(&mut self) -> bool {
-        if let Some(result) = self.current_data.try_resolve() {
-            match result {
-                Ok(data) => {
-                    self.current_data = DataContainer::Some(data);
-                }
-                Err(err) => {
-                    self.handle_action(Action::LogError(err));
-                    self.current_data = DataContainer::None;
-                }
-            };
-        };
-        self.current_data.pending()
+        let mut pending = false;
+        let mut errors = vec![];
+        for (_, tab) in self.tabs.iter_all_tabs_mut() {
+            let error = tab.check_data_future(&self.column_layouts);
+            tab.update_history(&mut self.history, error.as_ref());
+            errors.extend(error);
+            pending |= tab.data.pending();
+        }
+        for err in errors {
+            self.handle_action(Action::LogError(err));
+        }
+        pending
     }
 }
 
 impl eframe::App for ParqBenchApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, STORAGE_KEY, &self.history);
+        eframe::set_value(storage, SETTINGS_KEY, &self.settings);
+        eframe::set_value(storage, COLUMN_LAYOUTS_KEY, &self.column_layouts);
+    }
+
     fn update(&mut self, ctx: &egui::Context, _: &mut eframe::Frame) {
         //////////
         // Frame setup. Check if various interactions are in progress and resolve them
@@ -216,7 +795,9 @@ impl eframe::App for ParqBenchApp {
         self.check_error_channel();
         self.check_floating_displays(ctx);
         let loading = self.check_data_future();
-        if loading {
+        let exporting = self.check_exports();
+        let opening = self.check_workspace();
+        if loading || exporting || opening {
             ctx.request_repaint();
         }
 
@@ -235,14 +816,14 @@ impl eframe::App for ParqBenchApp {
         //////////
 
         //////////
-        //   Using static layout until I put together a TabTree that can make this dynamic
+        //   Results open as tabs in the main area, which can be split and rearranged
         //
         //   | header              |
         //   -----------------------
-        //   |       |             |
-        //   | query |     main    |
-        //   | info  |     table   |
-        //   |       |             |
+        //   |       | tab | tab   |
+        //   | query |-------------|
+        //   | info  |    main     |
+        //   |       |    table    |
         //   -----------------------
         //   | notification footer |
         //
@@ -250,6 +831,16 @@ impl eframe::App for ParqBenchApp {
 
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             ui.horizontal(|ui| {
+                ui.menu_button("Workspace", |ui| {
+                    if ui.button("Open...").clicked() {
+                        self.popover = Some(Box::new(WorkspaceDialog::open()));
+                        ui.close_menu();
+                    }
+                    if ui.button("Save...").clicked() {
+                        self.popover = Some(Box::new(WorkspaceDialog::save()));
+                        ui.close_menu();
+                    }
+                });
                 egui::warn_if_debug_build(ui);
                 ui.with_layout(Layout::right_to_left(egui::Align::Center), |ui| {
                     if ui.button("⚙").clicked() {
@@ -260,22 +851,60 @@ impl eframe::App for ParqBenchApp {
         });
 
         egui::TopBottomPanel::bottom("bottom_panel").show(ctx, |ui| {
-            ui.horizontal(|ui| {
-                if let Some(err) = self.errors.last() {
-                    let text = egui::RichText::new(format!("⚠ {}: {}", self.errors.len(), err))
-                        .color(ui.style().visuals.error_fg_color);
-                    if ui
-                        .add(
-                            egui::Label::new(text)
-                                .truncate(true)
-                                .sense(egui::Sense::click()),
-                        )
-                        .clicked()
-                    {
-                        // FIXME: use an action for self-referential popovers
-                        self.display_states.error = !self.display_states.error;
+            ui.with_layout(Layout::right_to_left(egui::Align::Center), |ui| {
+                // row count of the focused tab is pinned right, errors fill the remaining space
+                if let Some((
+                    _,
+                    ResultTab {
+                        data: DataContainer::Some(data),
+                        ..
+                    },
+                )) = self.tabs.find_active_focused()
+                {
+                    if let Some(count) = data.row_count() {
+                        ui.label(format!("{} rows", count));
+                    } else if data.is_counting() {
+                        if ui.small_button("Cancel").clicked() {
+                            data.cancel_count();
+                        }
+                        ui.spinner();
+                        ui.label("counting rows");
                     }
-                };
+                }
+                for export in self.exports.iter() {
+                    let text = format!("exporting {}: {} rows", export.path, export.rows_written());
+                    match export.total_rows {
+                        Some(total) if total > 0 => {
+                            let progress = export.rows_written() as f32 / total as f32;
+                            ui.add(
+                                egui::ProgressBar::new(progress)
+                                    .desired_width(ui.style().spacing.text_edit_width)
+                                    .text(text),
+                            );
+                        }
+                        _ => {
+                            ui.spinner();
+                            ui.label(text);
+                        }
+                    }
+                }
+                ui.with_layout(Layout::left_to_right(egui::Align::Center), |ui| {
+                    if let Some(err) = self.errors.last() {
+                        let text = egui::RichText::new(format!("⚠ {}: {}", self.errors.len(), err))
+                            .color(ui.style().visuals.error_fg_color);
+                        if ui
+                            .add(
+                                egui::Label::new(text)
+                                    .truncate(true)
+                                    .sense(egui::Sense::click()),
+                            )
+                            .clicked()
+                        {
+                            // FIXME: use an action for self-referential popovers
+                            self.display_states.error = !self.display_states.error;
+                        }
+                    };
+                });
             });
         });
 
@@ -288,9 +917,11 @@ impl eframe::App for ParqBenchApp {
                         ui.heading("Data Sources");
                         ui.end_row();
                         ui.vertical(|ui| {
-                            let action =
-                                smol::block_on(self.data_source.write_blocking().list_tables())
-                                    .show(ui);
+                            let mut data_source = self.data_source.write_blocking();
+                            self.query
+                                .set_tables(smol::block_on(data_source.list_tables()));
+                            let action = data_source.show(ui);
+                            drop(data_source);
                             if let Some(action) = action {
                                 self.handle_action(action)
                             }
@@ -305,27 +936,37 @@ impl eframe::App for ParqBenchApp {
                             }
                         });
                         ui.end_row();
+                        ui.end_row();
+                        ui.heading("Queries");
+                        ui.end_row();
+                        ui.vertical(|ui| {
+                            if let Some(action) = self.history.show(ui) {
+                                self.handle_action(action);
+                            }
+                        });
+                        ui.end_row();
                     });
                 });
             });
 
         egui::CentralPanel::default().show(ctx, |ui| {
-            // TODO: move the horizontal scroll into the table
-            egui::ScrollArea::horizontal().show(ui, |ui| {
-                if let DataContainer::Some(ref data) = self.current_data {
-                    if let Some(action) = data.show(ui) {
-                        self.handle_action(action);
-                    }
-                } else if loading {
-                    ui.centered_and_justified(|ui| {
-                        ui.spinner();
-                    });
-                } else {
-                    ui.centered_and_justified(|ui| {
-                        ui.label("Drag and drop file or directory here");
-                    });
-                }
-            });
+            if self.tabs.iter_all_tabs().next().is_none() {
+                ui.centered_and_justified(|ui| {
+                    ui.label("Drag and drop file or directory here");
+                });
+                return;
+            }
+
+            let mut viewer = ResultTabViewer {
+                actions: vec![],
+                formats: &self.settings.formats,
+            };
+            DockArea::new(&mut self.tabs)
+                .style(egui_dock::Style::from_egui(ui.style()))
+                .show_inside(ui, &mut viewer);
+            for action in viewer.actions {
+                self.handle_action(action);
+            }
         });
     }
 }
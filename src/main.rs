@@ -1,9 +1,21 @@
 #![warn(clippy::all, rust_2018_idioms)]
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
+pub mod chart;
+pub mod cli;
+pub mod clipboard;
+pub mod columns;
+pub mod compare;
 pub mod components;
 pub mod data;
+pub mod display;
+pub mod explain;
+pub mod export;
+pub mod history;
 pub mod layout;
+pub mod merge;
+pub mod profile;
+pub mod workspace;
 
 use crate::components::Action;
 use structopt::StructOpt;
@@ -13,11 +25,13 @@ use structopt::StructOpt;
 struct Args {
     #[structopt()]
     filename: Option<String>,
-    // #[structopt(short, long, requires("filename"))]
-    // query: Option<String>,
 
-    // #[structopt(short, long, requires_all(&["filename", "query"]))]
-    // table_name: Option<TableName>,
+    /// Workspace file to restore sources, the query and open results from.
+    #[structopt(long, parse(from_os_str))]
+    workspace: Option<std::path::PathBuf>,
+
+    #[structopt(subcommand)]
+    command: Option<cli::Command>,
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -26,9 +40,21 @@ fn main() {
 
     use data::TableDescriptor;
     use eframe::icon_data::from_png_bytes;
-    tracing_subscriber::fmt::init();
 
     let args = Args::from_args();
+    if let Some(command) = args.command {
+        // keep stdout clean for the command output
+        tracing_subscriber::fmt()
+            .with_writer(std::io::stderr)
+            .init();
+        if let Err(err) = cli::run(command) {
+            eprintln!("Error: {:#}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    tracing_subscriber::fmt::init();
     let icon =
         from_png_bytes(include_bytes!("../assets/icon-circle.png")).expect("Failed to load icon");
 
@@ -45,6 +71,9 @@ fn main() {
         options,
         Box::new(move |cc| {
             let mut app = layout::ParqBenchApp::new(cc);
+            if let Some(workspace) = args.workspace {
+                app.handle_action(Action::OpenWorkspace(workspace));
+            }
             if let Some(filename) = args.filename {
                 let table =
                     TableDescriptor::new(&filename).expect("Could not build table from filename");
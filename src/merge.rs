@@ -0,0 +1,289 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+
+/// Largest precision of a 128 bit decimal.
+const MAX_DECIMAL128_PRECISION: u8 = 38;
+
+/// How the schema of one file differs from the merged schema of its source.
+pub struct FileSchemaDiff {
+    pub file: String,
+    /// Columns the file doesn't have, which are read as nulls.
+    pub missing: Vec<String>,
+    /// Columns stored as a narrower type, and the type in the file.
+    pub widened: Vec<(String, DataType)>,
+}
+
+fn integer_width(data_type: &DataType) -> Option<(bool, u8)> {
+    match data_type {
+        DataType::Int8 => Some((true, 8)),
+        DataType::Int16 => Some((true, 16)),
+        DataType::Int32 => Some((true, 32)),
+        DataType::Int64 => Some((true, 64)),
+        DataType::UInt8 => Some((false, 8)),
+        DataType::UInt16 => Some((false, 16)),
+        DataType::UInt32 => Some((false, 32)),
+        DataType::UInt64 => Some((false, 64)),
+        _ => None,
+    }
+}
+
+fn integer_type(signed: bool, width: u8) -> Option<DataType> {
+    match (signed, width) {
+        (true, 8) => Some(DataType::Int8),
+        (true, 16) => Some(DataType::Int16),
+        (true, 32) => Some(DataType::Int32),
+        (true, 64) => Some(DataType::Int64),
+        (false, 8) => Some(DataType::UInt8),
+        (false, 16) => Some(DataType::UInt16),
+        (false, 32) => Some(DataType::UInt32),
+        (false, 64) => Some(DataType::UInt64),
+        _ => None,
+    }
+}
+
+/// The narrowest type that values of either type can be cast to, if there is one.
+fn widen(a: &DataType, b: &DataType) -> Option<DataType> {
+    use DataType::*;
+
+    if a == b {
+        return Some(a.clone());
+    }
+    if let (Some((a_signed, a_width)), Some((b_signed, b_width))) =
+        (integer_width(a), integer_width(b))
+    {
+        return if a_signed == b_signed {
+            integer_type(a_signed, a_width.max(b_width))
+        } else {
+            // unsigned values need a wider signed type to fit
+            let (signed_width, unsigned_width) = if a_signed {
+                (a_width, b_width)
+            } else {
+                (b_width, a_width)
+            };
+            integer_type(true, signed_width.max(unsigned_width * 2))
+        };
+    }
+    match (a, b) {
+        (Null, other) | (other, Null) => Some(other.clone()),
+        (Float16 | Float32 | Float64, Float16 | Float32 | Float64) => {
+            Some(if matches!(a, Float64) || matches!(b, Float64) {
+                Float64
+            } else {
+                Float32
+            })
+        }
+        (Float16 | Float32 | Float64, _) | (_, Float16 | Float32 | Float64)
+            if a.is_integer() || b.is_integer() =>
+        {
+            Some(Float64)
+        }
+        (Utf8 | LargeUtf8, Utf8 | LargeUtf8) => Some(LargeUtf8),
+        (Binary | LargeBinary, Binary | LargeBinary) => Some(LargeBinary),
+        (Date32 | Date64, Date32 | Date64) => Some(Date64),
+        (Decimal128(a_precision, a_scale), Decimal128(b_precision, b_scale)) => {
+            let scale = *a_scale.max(b_scale);
+            let integer_digits =
+                (*a_precision as i16 - *a_scale as i16).max(*b_precision as i16 - *b_scale as i16);
+            let precision = integer_digits + scale as i16;
+            (precision <= MAX_DECIMAL128_PRECISION as i16)
+                .then_some(Decimal128(precision as u8, scale))
+        }
+        (List(a_item), List(b_item)) => {
+            let item = widen(a_item.data_type(), b_item.data_type())?;
+            Some(List(Arc::new(
+                a_item
+                    .as_ref()
+                    .clone()
+                    .with_data_type(item)
+                    .with_nullable(a_item.is_nullable() || b_item.is_nullable()),
+            )))
+        }
+        (LargeList(a_item), LargeList(b_item)) => {
+            let item = widen(a_item.data_type(), b_item.data_type())?;
+            Some(LargeList(Arc::new(
+                a_item
+                    .as_ref()
+                    .clone()
+                    .with_data_type(item)
+                    .with_nullable(a_item.is_nullable() || b_item.is_nullable()),
+            )))
+        }
+        _ => None,
+    }
+}
+
+/// Merge the schemas of the files of a source by column name. Columns are ordered by where they
+/// first appear, are nullable unless every file has them, and are widened to fit every file.
+pub fn merge_schemas(
+    files: &[(String, SchemaRef)],
+) -> anyhow::Result<(Schema, Vec<FileSchemaDiff>)> {
+    // the merged field, and the file it was last widened by, for errors
+    let mut fields: Vec<(Field, &str)> = vec![];
+    let mut metadata = BTreeMap::new();
+    for (file, schema) in files {
+        metadata.extend(schema.metadata().clone());
+        for field in schema.fields() {
+            match fields
+                .iter_mut()
+                .find(|(merged, _)| merged.name() == field.name())
+            {
+                Some((merged, from)) => {
+                    let data_type =
+                        widen(merged.data_type(), field.data_type()).ok_or_else(|| {
+                            anyhow!(
+                                "Column {} is {} in {} but {} in {}",
+                                field.name(),
+                                merged.data_type(),
+                                from,
+                                field.data_type(),
+                                file
+                            )
+                        })?;
+                    if data_type != *merged.data_type() {
+                        *from = file;
+                    }
+                    let nullable = merged.is_nullable() || field.is_nullable();
+                    *merged = merged
+                        .clone()
+                        .with_data_type(data_type)
+                        .with_nullable(nullable);
+                }
+                None => fields.push((field.as_ref().clone(), file)),
+            }
+        }
+    }
+
+    let mut diffs = vec![];
+    for (file, schema) in files {
+        let mut diff = FileSchemaDiff {
+            file: file.clone(),
+            missing: vec![],
+            widened: vec![],
+        };
+        for (merged, _) in fields.iter_mut() {
+            match schema.field_with_name(merged.name()) {
+                Ok(field) if field.data_type() != merged.data_type() => diff
+                    .widened
+                    .push((field.name().clone(), field.data_type().clone())),
+                Ok(_) => {}
+                Err(_) => {
+                    diff.missing.push(merged.name().clone());
+                    *merged = merged.clone().with_nullable(true);
+                }
+            }
+        }
+        if !diff.missing.is_empty() || !diff.widened.is_empty() {
+            diffs.push(diff);
+        }
+    }
+
+    let schema = Schema::new(
+        fields
+            .into_iter()
+            .map(|(field, _)| field)
+            .collect::<Vec<_>>(),
+    )
+    .with_metadata(metadata.into_iter().collect());
+    Ok((schema, diffs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str, fields: Vec<Field>) -> (String, SchemaRef) {
+        (name.to_owned(), Arc::new(Schema::new(fields)))
+    }
+
+    fn merged_type(a: DataType, b: DataType) -> anyhow::Result<DataType> {
+        let (schema, _) = merge_schemas(&[
+            file("a", vec![Field::new("x", a, false)]),
+            file("b", vec![Field::new("x", b, false)]),
+        ])?;
+        Ok(schema.field(0).data_type().clone())
+    }
+
+    #[test]
+    fn widens_integers() {
+        use DataType::*;
+        assert_eq!(merged_type(Int8, Int32).unwrap(), Int32);
+        assert_eq!(merged_type(UInt16, UInt8).unwrap(), UInt16);
+        assert_eq!(merged_type(Int16, UInt16).unwrap(), Int32);
+        assert_eq!(merged_type(UInt8, Int64).unwrap(), Int64);
+        // no signed type holds every unsigned 64 bit value
+        assert!(merged_type(Int64, UInt64).is_err());
+    }
+
+    #[test]
+    fn widens_floats_and_decimals() {
+        use DataType::*;
+        assert_eq!(merged_type(Float32, Float64).unwrap(), Float64);
+        assert_eq!(merged_type(Int32, Float32).unwrap(), Float64);
+        assert_eq!(
+            merged_type(Decimal128(5, 2), Decimal128(10, 4)).unwrap(),
+            Decimal128(10, 4)
+        );
+        // 7 integer digits and 3 decimals
+        assert_eq!(
+            merged_type(Decimal128(9, 2), Decimal128(5, 3)).unwrap(),
+            Decimal128(10, 3)
+        );
+        assert!(merged_type(Decimal128(38, 0), Decimal128(38, 10)).is_err());
+    }
+
+    #[test]
+    fn widens_other_types() {
+        use DataType::*;
+        assert_eq!(merged_type(Utf8, LargeUtf8).unwrap(), LargeUtf8);
+        assert_eq!(merged_type(Date32, Date64).unwrap(), Date64);
+        assert_eq!(merged_type(Null, Boolean).unwrap(), Boolean);
+        assert_eq!(
+            merged_type(
+                List(Arc::new(Field::new("item", Int32, false))),
+                List(Arc::new(Field::new("item", Int64, true)))
+            )
+            .unwrap(),
+            List(Arc::new(Field::new("item", Int64, true)))
+        );
+        assert!(merged_type(Utf8, Int64).is_err());
+    }
+
+    #[test]
+    fn reports_missing_and_widened_columns() {
+        let (schema, diffs) = merge_schemas(&[
+            file(
+                "old",
+                vec![
+                    Field::new("id", DataType::Int32, false),
+                    Field::new("name", DataType::Utf8, false),
+                ],
+            ),
+            file(
+                "new",
+                vec![
+                    Field::new("id", DataType::Int64, false),
+                    Field::new("added", DataType::Boolean, false),
+                ],
+            ),
+        ])
+        .unwrap();
+        assert_eq!(
+            schema,
+            Schema::new(vec![
+                Field::new("id", DataType::Int64, false),
+                Field::new("name", DataType::Utf8, true),
+                Field::new("added", DataType::Boolean, true),
+            ])
+        );
+        assert_eq!(diffs.len(), 2);
+        assert_eq!(diffs[0].file, "old");
+        assert_eq!(diffs[0].missing, vec!["added"]);
+        assert_eq!(diffs[0].widened, vec![("id".to_owned(), DataType::Int32)]);
+        assert_eq!(diffs[1].file, "new");
+        assert_eq!(diffs[1].missing, vec!["name"]);
+        assert!(diffs[1].widened.is_empty());
+    }
+}
@@ -0,0 +1,340 @@
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use datafusion::arrow::array::{Array, ArrayRef, AsArray, Int64Array};
+use datafusion::arrow::compute::cast;
+use datafusion::arrow::datatypes::{DataType, Float64Type, Int64Type};
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::arrow::util::display::array_value_to_string;
+use datafusion::functions::expr_fn::floor;
+use datafusion::logical_expr::{
+    approx_distinct, avg, cast as cast_expr, count, count_distinct, max, min, stddev, when, Expr,
+};
+use datafusion::prelude::{ident, lit, DataFrame};
+
+/// Most frequent values listed in a profile.
+const TOP_VALUES: usize = 10;
+/// Buckets in the histogram of numeric and temporal columns.
+const HISTOGRAM_BINS: usize = 20;
+
+/// Summary statistics of a single column of a result.
+pub struct ColumnProfile {
+    pub column: String,
+    pub data_type: DataType,
+    pub rows: i64,
+    pub nulls: i64,
+    pub distinct: i64,
+    pub distinct_exact: bool,
+    pub min: Option<String>,
+    pub max: Option<String>,
+    pub mean: Option<f64>,
+    pub stddev: Option<f64>,
+    pub top_values: Vec<(String, i64)>,
+    /// Start of each bucket, as a number, and the count of values in it.
+    pub histogram: Vec<(f64, i64)>,
+    pub bin_width: f64,
+}
+
+pub fn has_histogram(data_type: &DataType) -> bool {
+    data_type.is_numeric()
+        || matches!(
+            data_type,
+            DataType::Date32
+                | DataType::Date64
+                | DataType::Timestamp(_, _)
+                | DataType::Time32(_)
+                | DataType::Time64(_)
+                | DataType::Duration(_)
+        )
+}
+
+fn approx_distinct_text(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Binary | DataType::LargeBinary
+    )
+}
+
+/// The integer type that a temporal type can be cast to and from, as times of day in seconds
+/// or milliseconds can only be cast to 32 bit integers.
+fn temporal_storage_type(data_type: &DataType) -> DataType {
+    match data_type {
+        DataType::Time32(_) => DataType::Int32,
+        _ => DataType::Int64,
+    }
+}
+
+/// The column as a float, so that temporal columns can be binned like numbers.
+pub fn as_float(column: Expr, data_type: &DataType) -> Expr {
+    if data_type.is_numeric() {
+        cast_expr(column, DataType::Float64)
+    } else {
+        cast_expr(
+            cast_expr(column, temporal_storage_type(data_type)),
+            DataType::Float64,
+        )
+    }
+}
+
+/// Converts a number from a histogram back into the column's type for display.
+pub fn format_bin(value: f64, data_type: &DataType) -> String {
+    if data_type.is_numeric() {
+        return format!("{}", value);
+    }
+    let array: ArrayRef = Arc::new(Int64Array::from(vec![value as i64]));
+    cast(&array, &temporal_storage_type(data_type))
+        .and_then(|array| cast(&array, data_type))
+        .ok()
+        .and_then(|array| array_value_to_string(&array, 0).ok())
+        .unwrap_or_else(|| format!("{}", value))
+}
+
+fn single_row(batches: &[RecordBatch]) -> anyhow::Result<&RecordBatch> {
+    batches
+        .iter()
+        .find(|batch| batch.num_rows() > 0)
+        .ok_or(anyhow!("Profile query returned no rows"))
+}
+
+fn int_value(array: &ArrayRef) -> anyhow::Result<i64> {
+    let array = cast(array, &DataType::Int64)?;
+    let array = array.as_primitive::<Int64Type>();
+    Ok(if array.is_null(0) { 0 } else { array.value(0) })
+}
+
+fn float_value(array: &ArrayRef) -> anyhow::Result<Option<f64>> {
+    let array = cast(array, &DataType::Float64)?;
+    let array = array.as_primitive::<Float64Type>();
+    Ok((!array.is_null(0)).then(|| array.value(0)))
+}
+
+fn string_value(array: &ArrayRef, row: usize) -> anyhow::Result<Option<String>> {
+    Ok(if array.is_null(row) {
+        None
+    } else {
+        Some(array_value_to_string(array, row)?)
+    })
+}
+
+/// Profile a column of `df` with a handful of aggregate queries.
+pub async fn profile_column(
+    df: DataFrame,
+    column: String,
+    exact: bool,
+) -> anyhow::Result<ColumnProfile> {
+    let data_type = df
+        .schema()
+        .field_with_unqualified_name(&column)?
+        .data_type()
+        .clone();
+    let value = ident(&column);
+    let numeric = has_histogram(&data_type);
+
+    // the approximation only supports integers and strings, other types are always counted
+    let exact = exact || !(data_type.is_integer() || approx_distinct_text(&data_type));
+    let mut aggregates = vec![
+        count(lit(1)).alias("rows"),
+        count(value.clone()).alias("non_null"),
+        if exact {
+            count_distinct(value.clone()).alias("distinct")
+        } else {
+            approx_distinct(value.clone()).alias("distinct")
+        },
+    ];
+    // nested values have no ordering, and DataFusion can't take the minimum of some types
+    let ordered =
+        !data_type.is_nested() && !matches!(data_type, DataType::Float16 | DataType::Duration(_));
+    if ordered {
+        aggregates.extend([
+            min(value.clone()).alias("min"),
+            max(value.clone()).alias("max"),
+        ]);
+    }
+    if numeric {
+        let float = as_float(value.clone(), &data_type);
+        aggregates.extend([
+            avg(float.clone()).alias("mean"),
+            stddev(float.clone()).alias("stddev"),
+            min(float.clone()).alias("low"),
+            max(float).alias("high"),
+        ]);
+    }
+    let batches = df.clone().aggregate(vec![], aggregates)?.collect().await?;
+    let stats = single_row(&batches)?;
+    let rows = int_value(stats.column(0))?;
+    let non_null = int_value(stats.column(1))?;
+    let distinct = int_value(stats.column(2))?;
+    let (mean, std, low, high) = if numeric {
+        let offset = stats.num_columns() - 4;
+        (
+            float_value(stats.column(offset))?,
+            float_value(stats.column(offset + 1))?,
+            float_value(stats.column(offset + 2))?,
+            float_value(stats.column(offset + 3))?,
+        )
+    } else {
+        (None, None, None, None)
+    };
+    let (min_value, max_value) = if ordered {
+        (
+            string_value(stats.column(3), 0)?,
+            string_value(stats.column(4), 0)?,
+        )
+    } else {
+        // from the numeric bounds of the values instead
+        (
+            low.map(|low| format_bin(low, &data_type)),
+            high.map(|high| format_bin(high, &data_type)),
+        )
+    };
+
+    let mut top_values = vec![];
+    if !data_type.is_nested() {
+        let batches = df
+            .clone()
+            .aggregate(vec![value.clone()], vec![count(lit(1)).alias("count")])?
+            .sort(vec![
+                ident("count").sort(false, false),
+                value.clone().sort(true, false),
+            ])?
+            .limit(0, Some(TOP_VALUES))?
+            .collect()
+            .await?;
+        for batch in batches {
+            for row in 0..batch.num_rows() {
+                let text = string_value(batch.column(0), row)?.unwrap_or("null".to_owned());
+                let count = int_value(&batch.column(1).slice(row, 1))?;
+                top_values.push((text, count));
+            }
+        }
+    }
+
+    let mut histogram = vec![];
+    let mut bin_width = 0.0;
+    if let (Some(low), Some(high)) = (low, high) {
+        bin_width = (high - low) / HISTOGRAM_BINS as f64;
+        let bin = if bin_width > 0.0 {
+            let bin = floor((as_float(value.clone(), &data_type) - lit(low)) / lit(bin_width));
+            // the maximum falls on the end of the last bin, rather than the start of a new one
+            when(
+                bin.clone().gt_eq(lit((HISTOGRAM_BINS - 1) as f64)),
+                lit((HISTOGRAM_BINS - 1) as f64),
+            )
+            .otherwise(bin)?
+        } else {
+            bin_width = 1.0;
+            lit(0.0)
+        };
+        let batches = df
+            .filter(value.clone().is_not_null())?
+            .aggregate(vec![bin.alias("bin")], vec![count(lit(1)).alias("count")])?
+            .sort(vec![ident("bin").sort(true, false)])?
+            .collect()
+            .await?;
+        for batch in batches {
+            for row in 0..batch.num_rows() {
+                let bin = float_value(&batch.column(0).slice(row, 1))?.unwrap_or_default();
+                let count = int_value(&batch.column(1).slice(row, 1))?;
+                histogram.push((low + bin * bin_width, count));
+            }
+        }
+    }
+
+    let is_numeric = data_type.is_numeric();
+    Ok(ColumnProfile {
+        column,
+        data_type,
+        rows,
+        nulls: rows - non_null,
+        distinct,
+        distinct_exact: exact,
+        min: min_value,
+        max: max_value,
+        mean,
+        // the spread of temporal values isn't meaningful as a raw number
+        stddev: std.filter(|_| is_numeric),
+        top_values,
+        histogram,
+        bin_width,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use async_compat::Compat;
+    use datafusion::arrow::array::Int32Array;
+    use datafusion::arrow::datatypes::TimeUnit;
+    use datafusion::prelude::SessionContext;
+
+    use super::*;
+
+    /// The values 0 to 9 as `data_type`.
+    fn values(data_type: &DataType) -> ArrayRef {
+        let array: ArrayRef = Arc::new(Int32Array::from_iter_values(0..10));
+        let array = cast(&array, &temporal_storage_type(data_type)).unwrap();
+        cast(&array, data_type).unwrap()
+    }
+
+    #[test]
+    fn histograms_of_every_type() {
+        use DataType::*;
+        let types = [
+            Int8,
+            Int16,
+            Int32,
+            Int64,
+            UInt8,
+            UInt16,
+            UInt32,
+            UInt64,
+            Float16,
+            Float32,
+            Float64,
+            Decimal128(10, 2),
+            Decimal256(40, 2),
+            Date32,
+            Date64,
+            Timestamp(TimeUnit::Millisecond, None),
+            Timestamp(TimeUnit::Nanosecond, Some("+02:00".into())),
+            Time32(TimeUnit::Second),
+            Time32(TimeUnit::Millisecond),
+            Time64(TimeUnit::Microsecond),
+            Time64(TimeUnit::Nanosecond),
+            Duration(TimeUnit::Second),
+        ];
+        let ctx = SessionContext::new();
+        for data_type in types {
+            assert!(has_histogram(&data_type), "{data_type}");
+            let batch = RecordBatch::try_from_iter([("x", values(&data_type))]).unwrap();
+            let df = ctx.read_batch(batch).unwrap();
+            let profile = smol::block_on(Compat::new(profile_column(df, "x".to_owned(), true)))
+                .unwrap_or_else(|err| panic!("{data_type}: {err}"));
+            assert_eq!(
+                profile
+                    .histogram
+                    .iter()
+                    .map(|(_, count)| count)
+                    .sum::<i64>(),
+                10,
+                "{data_type}"
+            );
+            assert!(!format_bin(profile.histogram[0].0, &data_type).is_empty());
+        }
+    }
+
+    #[test]
+    fn bins_are_shown_as_the_column_type() {
+        use DataType::*;
+        assert_eq!(format_bin(1.5, &Float64), "1.5");
+        assert_eq!(format_bin(1.0, &Date32), "1970-01-02");
+        assert_eq!(format_bin(61.0, &Time32(TimeUnit::Second)), "00:01:01");
+        assert_eq!(
+            format_bin(1500.0, &Time32(TimeUnit::Millisecond)),
+            "00:00:01.500"
+        );
+        assert_eq!(
+            format_bin(1_000_000.0, &Time64(TimeUnit::Microsecond)),
+            "00:00:01"
+        );
+    }
+}
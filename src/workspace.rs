@@ -0,0 +1,62 @@
+use std::path::Path;
+
+use crate::data::{Query, TableDescriptor};
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+/// A result that was open when the workspace was saved.
+#[derive(Serialize, Deserialize)]
+pub struct View {
+    pub title: String,
+    pub query: Query,
+}
+
+/// A result saved as a view, registered again from its SQL when the workspace is opened.
+#[derive(Serialize, Deserialize)]
+pub struct SavedView {
+    pub name: String,
+    pub sql: String,
+}
+
+/// The sources, query and open results of an investigation, so it can be picked up again
+/// or shared. Written as JSON if the file ends in `.json`, and as TOML otherwise.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Workspace {
+    #[serde(default)]
+    pub query: String,
+    #[serde(default)]
+    pub sources: Vec<TableDescriptor>,
+    /// Registered after the sources, in order, as each may read the ones before it.
+    #[serde(default)]
+    pub saved_views: Vec<SavedView>,
+    #[serde(default)]
+    pub views: Vec<View>,
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "json")
+}
+
+impl Workspace {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| anyhow!("Could not read workspace {}: {}", path.display(), err))?;
+        let workspace = if is_json(path) {
+            serde_json::from_str(&text)?
+        } else {
+            toml::from_str(&text)?
+        };
+        Ok(workspace)
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let text = if is_json(path) {
+            serde_json::to_string_pretty(self)?
+        } else {
+            toml::to_string_pretty(self)?
+        };
+        std::fs::write(path, text)
+            .map_err(|err| anyhow!("Could not write workspace {}: {}", path.display(), err))
+    }
+}